@@ -0,0 +1,259 @@
+use std::path::Path;
+use image::DynamicImage;
+
+/// How the brush influence decays between the inner and the outer radius
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Falloff {
+    Constant,
+    Linear,
+    Smoothstep,
+    Gaussian
+}
+
+impl Falloff {
+    /// `t` is 0 at the inner radius and 1 at the outer one
+    pub fn evaluate(self, t: f32) -> f32 {
+        if t <= 0.0 {
+            return 1.0;
+        }
+        if t >= 1.0 {
+            return 0.0;
+        }
+        match self {
+            Falloff::Constant => 1.0,
+            Falloff::Linear => 1.0 - t,
+            Falloff::Smoothstep => 1.0 - t * t * (3.0 - 2.0 * t),
+            // scaled so the curve reaches exactly zero at the outer radius
+            Falloff::Gaussian => {
+                let edge = (-4.0f32).exp();
+                ((-4.0 * t * t).exp() - edge) / (1.0 - edge)
+            }
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Falloff::Constant => Falloff::Linear,
+            Falloff::Linear => Falloff::Smoothstep,
+            Falloff::Smoothstep => Falloff::Gaussian,
+            Falloff::Gaussian => Falloff::Constant
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct BrushSettings {
+    /// Outer radius in world units, the brush has no influence beyond it
+    pub radius: f32,
+    /// Inner radius relative to the outer one, the influence is full inside of it
+    pub hardness: f32,
+    /// Weight change per stamp at full influence
+    pub strength: f32,
+    /// Distance between stamps along the stroke relative to the radius
+    pub spacing: f32,
+    pub falloff: Falloff
+}
+
+impl BrushSettings {
+    /// Distance between stamps in world units
+    pub fn stamp_spacing(&self) -> f32 {
+        self.radius * self.spacing
+    }
+
+    pub fn inner_radius(&self) -> f32 {
+        self.radius * self.hardness.clamp(0.0, 1.0)
+    }
+
+    /// Falloff for a shape-specific distance from the brush center
+    pub fn falloff_at(&self, distance: f32) -> f32 {
+        let inner_radius = self.inner_radius();
+        if self.radius - inner_radius <= f32::EPSILON {
+            return if distance <= self.radius { 1.0 } else { 0.0 };
+        }
+        self.falloff.evaluate((distance - inner_radius) / (self.radius - inner_radius))
+    }
+}
+
+pub trait Brush {
+    fn settings(&self) -> &BrushSettings;
+
+    fn settings_mut(&mut self) -> &mut BrushSettings;
+
+    /// Influence in `0.0..=1.0` at `offset` from the brush center
+    fn influence(&self, offset: [f32; 2]) -> f32;
+
+    /// Closed outline of the brush shape scaled to `radius`, used for the cursor preview
+    fn outline(&self, radius: f32) -> Vec<[f32; 2]>;
+}
+
+pub struct CircleBrush {
+    settings: BrushSettings
+}
+
+impl CircleBrush {
+    pub fn new(settings: BrushSettings) -> Self {
+        Self { settings }
+    }
+}
+
+impl Brush for CircleBrush {
+    fn settings(&self) -> &BrushSettings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut BrushSettings {
+        &mut self.settings
+    }
+
+    fn influence(&self, offset: [f32; 2]) -> f32 {
+        self.settings.falloff_at((offset[0] * offset[0] + offset[1] * offset[1]).sqrt())
+    }
+
+    fn outline(&self, radius: f32) -> Vec<[f32; 2]> {
+        (0..72)
+            .map(|degree| (degree as f32 * 5.0).to_radians())
+            .map(|theta| [theta.cos() * radius, theta.sin() * radius])
+            .collect()
+    }
+}
+
+pub struct SquareBrush {
+    settings: BrushSettings
+}
+
+impl SquareBrush {
+    pub fn new(settings: BrushSettings) -> Self {
+        Self { settings }
+    }
+}
+
+fn square_outline(radius: f32) -> Vec<[f32; 2]> {
+    vec![[-radius, -radius], [radius, -radius], [radius, radius], [-radius, radius]]
+}
+
+impl Brush for SquareBrush {
+    fn settings(&self) -> &BrushSettings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut BrushSettings {
+        &mut self.settings
+    }
+
+    fn influence(&self, offset: [f32; 2]) -> f32 {
+        self.settings.falloff_at(offset[0].abs().max(offset[1].abs()))
+    }
+
+    fn outline(&self, radius: f32) -> Vec<[f32; 2]> {
+        square_outline(radius)
+    }
+}
+
+/// Brush shaped by a grayscale image stretched over its square bounds.
+/// The image brightness is multiplied by the falloff of a square brush
+pub struct StampBrush {
+    settings: BrushSettings,
+    mask: Vec<f32>,
+    mask_width: usize,
+    mask_height: usize
+}
+
+impl StampBrush {
+    pub fn from_image(image: &DynamicImage, settings: BrushSettings) -> Self {
+        let luma = image.to_luma8();
+        let (mask_width, mask_height) = (luma.width() as usize, luma.height() as usize);
+        Self {
+            settings,
+            mask: luma.pixels().map(|it| it[0] as f32 / 255.0).collect(),
+            mask_width,
+            mask_height
+        }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>, settings: BrushSettings) -> Result<Self, image::ImageError> {
+        Ok(Self::from_image(&image::open(path)?, settings))
+    }
+
+    /// Bilinear sample of the mask, `u` and `v` are in `0.0..=1.0`
+    fn sample_mask(&self, u: f32, v: f32) -> f32 {
+        if self.mask.is_empty() {
+            return 0.0;
+        }
+        let x = (u * self.mask_width as f32 - 0.5).clamp(0.0, (self.mask_width - 1) as f32);
+        let y = (v * self.mask_height as f32 - 0.5).clamp(0.0, (self.mask_height - 1) as f32);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.mask_width - 1), (y0 + 1).min(self.mask_height - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+        let at = |x: usize, y: usize| self.mask[y * self.mask_width + x];
+        let top = at(x0, y0) * (1.0 - tx) + at(x1, y0) * tx;
+        let bottom = at(x0, y1) * (1.0 - tx) + at(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+impl Brush for StampBrush {
+    fn settings(&self) -> &BrushSettings {
+        &self.settings
+    }
+
+    fn settings_mut(&mut self) -> &mut BrushSettings {
+        &mut self.settings
+    }
+
+    fn influence(&self, offset: [f32; 2]) -> f32 {
+        let radius = self.settings.radius;
+        if radius <= f32::EPSILON || offset[0].abs() > radius || offset[1].abs() > radius {
+            return 0.0;
+        }
+        let u = (offset[0] / radius + 1.0) / 2.0;
+        let v = (offset[1] / radius + 1.0) / 2.0;
+        self.sample_mask(u, v) * self.settings.falloff_at(offset[0].abs().max(offset[1].abs()))
+    }
+
+    fn outline(&self, radius: f32) -> Vec<[f32; 2]> {
+        square_outline(radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::brush::{Falloff, BrushSettings, CircleBrush, SquareBrush, StampBrush, Brush};
+    use crate::STAMP_BRUSH_PATH;
+
+    fn settings(falloff: Falloff) -> BrushSettings {
+        BrushSettings { radius: 10.0, hardness: 0.5, strength: 1.0, spacing: 0.25, falloff }
+    }
+
+    #[test]
+    pub fn test_falloff_curves_are_monotonic() {
+        for falloff in [Falloff::Constant, Falloff::Linear, Falloff::Smoothstep, Falloff::Gaussian] {
+            assert_eq!(1.0, falloff.evaluate(0.0));
+            assert_eq!(0.0, falloff.evaluate(1.0));
+            let mut previous = 1.0;
+            for step in 1..100 {
+                let value = falloff.evaluate(step as f32 / 100.0);
+                assert!(value <= previous && value >= 0.0);
+                previous = value;
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_shapes() {
+        let circle = CircleBrush::new(settings(Falloff::Linear));
+        let square = SquareBrush::new(settings(Falloff::Linear));
+
+        assert_eq!(1.0, circle.influence([3.0, 3.0]));
+        assert!((circle.influence([7.5, 0.0]) - 0.5).abs() < 0.0001);
+        assert_eq!(0.0, circle.influence([8.0, 8.0]));
+        assert!((square.influence([7.5, 7.5]) - 0.5).abs() < 0.0001);
+        assert_eq!(0.0, square.influence([10.5, 0.0]));
+    }
+
+    #[test]
+    pub fn test_stamp_asset_loads() {
+        let stamp = StampBrush::from_file(STAMP_BRUSH_PATH, settings(Falloff::Linear)).unwrap();
+        assert_eq!(1.0, stamp.influence([0.0, 0.0]));
+        assert_eq!(0.0, stamp.influence([9.5, 9.5]));
+    }
+}
@@ -0,0 +1,234 @@
+use crate::poly_line_2d::transform::Transform2D;
+
+/// 2D view of the world: `position` is the world point at the top left corner of the screen,
+/// `scale` is the number of pixels per world unit.
+/// Zooming and fitting ease towards a target state, panning is applied immediately
+#[derive(Copy, Clone, Debug)]
+pub struct EditorCamera {
+    position: [f32; 2],
+    scale: f32,
+    target_position: [f32; 2],
+    target_scale: f32,
+    /// Screen point which stays over the same world point while the zoom eases
+    zoom_anchor: Option<[f32; 2]>,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    /// Easing rate per second, zero or less disables easing
+    pub easing: f32
+}
+
+const SCALE_EPSILON: f32 = 0.0001;
+const SNAP_PIXELS: f32 = 0.01;
+
+impl EditorCamera {
+    pub fn new(position: [f32; 2], scale: f32) -> Self {
+        Self {
+            position,
+            scale,
+            target_position: position,
+            target_scale: scale,
+            zoom_anchor: None,
+            min_scale: 0.01,
+            max_scale: 10.0,
+            easing: 12.0
+        }
+    }
+
+    pub fn position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn screen_to_world(&self, point: [f32; 2]) -> [f32; 2] {
+        [
+            point[0] / self.scale + self.position[0],
+            point[1] / self.scale + self.position[1]
+        ]
+    }
+
+    pub fn world_to_screen(&self, point: [f32; 2]) -> [f32; 2] {
+        [
+            (point[0] - self.position[0]) * self.scale,
+            (point[1] - self.position[1]) * self.scale
+        ]
+    }
+
+    /// World to screen transform for the painter
+    pub fn transform(&self) -> Transform2D {
+        Transform2D::scaling(self.scale, self.scale) *
+            Transform2D::translation(-self.position[0], -self.position[1])
+    }
+
+    /// World-space corners of a screen of `screen_size` pixels
+    pub fn visible_rect(&self, screen_size: [f32; 2]) -> ([f32; 2], [f32; 2]) {
+        (self.position, self.screen_to_world(screen_size))
+    }
+
+    /// Moves the view by `delta` pixels so the world follows a dragging cursor
+    pub fn pan_by_screen(&mut self, delta: [f32; 2]) {
+        let world_delta = [delta[0] / self.scale, delta[1] / self.scale];
+        for position in [&mut self.position, &mut self.target_position] {
+            position[0] -= world_delta[0];
+            position[1] -= world_delta[1];
+        }
+    }
+
+    /// Multiplies the target scale by `factor` keeping the world point under `screen_point` in place
+    pub fn zoom_at(&mut self, screen_point: [f32; 2], factor: f32) {
+        let anchor_world = self.screen_to_world(screen_point);
+        self.target_scale = (self.target_scale * factor).clamp(self.min_scale, self.max_scale);
+        self.target_position = [
+            anchor_world[0] - screen_point[0] / self.target_scale,
+            anchor_world[1] - screen_point[1] / self.target_scale
+        ];
+        self.zoom_anchor = Some(screen_point);
+    }
+
+    /// Zooms by one `step` per wheel notch in the direction of `wheel_delta` at `screen_point`,
+    /// does nothing without wheel input so an easing zoom keeps its anchor
+    pub fn zoom_by_wheel(&mut self, screen_point: [f32; 2], wheel_delta: f32, step: f32) {
+        if wheel_delta != 0.0 {
+            self.zoom_at(screen_point, 1.0 + wheel_delta.signum() * step);
+        }
+    }
+
+    /// Targets the view which fits the world rectangle from `min` to `max` into the screen
+    /// with `margin` pixels on each side, centering it
+    pub fn fit(&mut self, min: [f32; 2], max: [f32; 2], screen_size: [f32; 2], margin: f32) {
+        let size = [(max[0] - min[0]).max(f32::EPSILON), (max[1] - min[1]).max(f32::EPSILON)];
+        let available = [(screen_size[0] - margin * 2.0).max(1.0), (screen_size[1] - margin * 2.0).max(1.0)];
+        self.target_scale = (available[0] / size[0])
+            .min(available[1] / size[1])
+            .clamp(self.min_scale, self.max_scale);
+        self.target_position = [
+            (min[0] + max[0]) / 2.0 - screen_size[0] / self.target_scale / 2.0,
+            (min[1] + max[1]) / 2.0 - screen_size[1] / self.target_scale / 2.0
+        ];
+        self.zoom_anchor = None;
+    }
+
+    /// Jumps to the target state
+    pub fn snap(&mut self) {
+        self.position = self.target_position;
+        self.scale = self.target_scale;
+        self.zoom_anchor = None;
+    }
+
+    /// Eases towards the target state, `dt` is in seconds
+    pub fn update(&mut self, dt: f32) {
+        if self.easing <= 0.0 {
+            self.snap();
+            return;
+        }
+        let t = 1.0 - (-self.easing * dt).exp();
+        // zoom is perceived multiplicatively, so ease the scale in log space
+        self.scale *= (self.target_scale / self.scale).powf(t);
+        if (self.scale / self.target_scale - 1.0).abs() < SCALE_EPSILON {
+            self.scale = self.target_scale;
+        }
+
+        match self.zoom_anchor {
+            Some(anchor) => {
+                let anchor_world = [
+                    anchor[0] / self.target_scale + self.target_position[0],
+                    anchor[1] / self.target_scale + self.target_position[1]
+                ];
+                self.position = [
+                    anchor_world[0] - anchor[0] / self.scale,
+                    anchor_world[1] - anchor[1] / self.scale
+                ];
+            }
+            None => {
+                self.position[0] += (self.target_position[0] - self.position[0]) * t;
+                self.position[1] += (self.target_position[1] - self.position[1]) * t;
+            }
+        }
+
+        let pixel_offset = [
+            (self.target_position[0] - self.position[0]) * self.scale,
+            (self.target_position[1] - self.position[1]) * self.scale
+        ];
+        if self.scale == self.target_scale && pixel_offset[0].abs().max(pixel_offset[1].abs()) < SNAP_PIXELS {
+            self.snap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::camera::EditorCamera;
+
+    fn assert_close(expected: [f32; 2], actual: [f32; 2]) {
+        assert!(
+            (expected[0] - actual[0]).abs() < 0.01 && (expected[1] - actual[1]).abs() < 0.01,
+            "{:?} != {:?}", expected, actual
+        );
+    }
+
+    #[test]
+    pub fn test_screen_world_round_trip() {
+        let camera = EditorCamera::new([-24.0, 100.0], 0.5);
+        assert_close([0.0, 0.0], camera.world_to_screen([-24.0, 100.0]));
+        assert_close([56.0, 260.0], camera.screen_to_world([40.0, 80.0]));
+        for point in [[0.0, 0.0], [123.0, -45.5], [800.0, 600.0]] {
+            assert_close(point, camera.world_to_screen(camera.screen_to_world(point)));
+            assert_close(point, camera.transform().apply(camera.screen_to_world(point)));
+        }
+        let (min, max) = camera.visible_rect([800.0, 600.0]);
+        assert_close([-24.0, 100.0], min);
+        assert_close([1576.0, 1300.0], max);
+    }
+
+    #[test]
+    pub fn test_zoom_keeps_cursor_point() {
+        let mut camera = EditorCamera::new([10.0, 20.0], 1.0);
+        let cursor = [300.0, 200.0];
+        let world = camera.screen_to_world(cursor);
+        camera.zoom_at(cursor, 2.0);
+        for _ in 0..5 {
+            camera.update(1.0 / 60.0);
+            assert!(camera.scale() > 1.0 && camera.scale() < 2.0);
+            assert_close(world, camera.screen_to_world(cursor));
+        }
+        camera.update(10.0);
+        assert_eq!(2.0, camera.scale());
+        assert_close(world, camera.screen_to_world(cursor));
+
+        camera.zoom_at(cursor, 1000.0);
+        camera.snap();
+        assert_eq!(camera.max_scale, camera.scale());
+        assert_close(world, camera.screen_to_world(cursor));
+    }
+
+    #[test]
+    pub fn test_no_wheel_input_keeps_zoom() {
+        let mut camera = EditorCamera::new([10.0, 20.0], 1.0);
+        camera.zoom_by_wheel([300.0, 200.0], 0.0, 0.1);
+        assert_eq!(1.0, camera.target_scale);
+        assert_eq!(None, camera.zoom_anchor);
+
+        camera.zoom_by_wheel([300.0, 200.0], -3.0, 0.1);
+        assert!((camera.target_scale - 0.9).abs() < 0.0001);
+        assert_eq!(Some([300.0, 200.0]), camera.zoom_anchor);
+        camera.zoom_by_wheel([0.0, 0.0], 0.0, 0.1);
+        assert!((camera.target_scale - 0.9).abs() < 0.0001);
+        assert_eq!(Some([300.0, 200.0]), camera.zoom_anchor);
+    }
+
+    #[test]
+    pub fn test_pan_and_fit() {
+        let mut camera = EditorCamera::new([0.0, 0.0], 2.0);
+        camera.pan_by_screen([20.0, -10.0]);
+        assert_close([-10.0, 5.0], camera.position());
+
+        camera.fit([0.0, 0.0], [1000.0, 500.0], [800.0, 600.0], 50.0);
+        camera.snap();
+        assert!((camera.scale() - 0.7).abs() < 0.0001);
+        assert_close([400.0, 300.0], camera.world_to_screen([500.0, 250.0]));
+        let (min, max) = camera.visible_rect([800.0, 600.0]);
+        assert!(min[0] < 0.0 && min[1] < 0.0 && max[0] > 1000.0 && max[1] > 500.0);
+    }
+}
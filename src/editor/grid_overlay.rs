@@ -0,0 +1,222 @@
+use macroquad::prelude::*;
+use crate::editor::camera::EditorCamera;
+use crate::poly_line_2d::Painter;
+use crate::poly_line_2d::style::{JointStyle, EndCapStyle, LineStripStyle, DashStyle, ThicknessSpace};
+
+#[derive(Copy, Clone)]
+pub struct GridLineStyle {
+    pub color: Color,
+    pub thickness: f32,
+    pub dash: DashStyle
+}
+
+/// Grid lines over a rectangular world area with a coordinate label next to every major line
+pub struct GridOverlay {
+    /// World-space corner where the grid starts
+    pub min: [f32; 2],
+    /// World-space corner where the grid ends
+    pub max: [f32; 2],
+    /// Distance between minor lines in world units
+    pub spacing: f32,
+    /// Every n-th minor line is a major one
+    pub major_every: usize,
+    /// Lines which would be closer than this on screen are hidden
+    pub min_pixel_spacing: f32,
+    pub minor: GridLineStyle,
+    pub major: GridLineStyle,
+    /// Font size of the labels, labels are hidden when it is zero
+    pub label_size: f32,
+    pub label_color: Color
+}
+
+/// Grid line position along one axis, `index` counts minor steps from the start of the grid
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GridLine {
+    pub index: usize,
+    pub coord: f32
+}
+
+impl GridOverlay {
+    /// Distances between minor and major lines shown at `scale` pixels per world unit.
+    /// Minor lines are hidden when too dense, major lines skip every other line until they are not
+    pub fn line_steps(&self, scale: f32) -> (Option<f32>, f32) {
+        let minor = if self.spacing * scale >= self.min_pixel_spacing {
+            Some(self.spacing)
+        } else {
+            None
+        };
+        let mut major = self.spacing * self.major_every.max(1) as f32;
+        while major * scale < self.min_pixel_spacing && major < f32::MAX / 2.0 {
+            major *= 2.0;
+        }
+        (minor, major)
+    }
+
+    pub fn draw(&self, painter: &mut Painter, camera: &EditorCamera, screen_size: [f32; 2]) {
+        if self.spacing <= 0.0 {
+            return;
+        }
+        let (visible_min, visible_max) = camera.visible_rect(screen_size);
+        let from = [self.min[0].max(visible_min[0]), self.min[1].max(visible_min[1])];
+        let to = [self.max[0].min(visible_max[0]), self.max[1].min(visible_max[1])];
+        if from[0] > to[0] || from[1] > to[1] {
+            return;
+        }
+
+        // dashes are offset by the clipped part so the pattern doesn't crawl while panning
+        let dash_scale = match painter.thickness_space() {
+            ThicknessSpace::World => 1.0,
+            ThicknessSpace::Screen => camera.scale()
+        };
+        let vertical_offset = (from[1] - self.min[1]) * dash_scale;
+        let horizontal_offset = (from[0] - self.min[0]) * dash_scale;
+
+        let (minor_step, major_step) = self.line_steps(camera.scale());
+        let major_every = (major_step / self.spacing).round() as usize;
+        let saved_dash_style = painter.dash_style();
+        painter.push_transform();
+        painter.apply_transform(camera.transform());
+
+        let mut passes = vec![(major_step, self.major, true)];
+        if let Some(minor_step) = minor_step {
+            passes.insert(0, (minor_step, self.minor, false));
+        }
+        for (step, style, is_major) in passes {
+            // minor lines under the major ones are drawn by the major pass
+            let skip_major = |line: &GridLine| !is_major && line.index.is_multiple_of(major_every);
+            painter.set_dash_style(with_offset(style.dash, vertical_offset));
+            for line in grid_lines(self.min[0], self.max[0], step, self.spacing, from[0], to[0]) {
+                if !skip_major(&line) {
+                    self.draw_line(painter, style, [line.coord, from[1]], [line.coord, to[1]]);
+                }
+            }
+            painter.set_dash_style(with_offset(style.dash, horizontal_offset));
+            for line in grid_lines(self.min[1], self.max[1], step, self.spacing, from[1], to[1]) {
+                if !skip_major(&line) {
+                    self.draw_line(painter, style, [from[0], line.coord], [to[0], line.coord]);
+                }
+            }
+        }
+
+        painter.pop_transform();
+        painter.set_dash_style(saved_dash_style);
+
+        if self.label_size > 0.0 {
+            self.draw_labels(camera, from, to, major_step);
+        }
+    }
+
+    fn draw_line(&self, painter: &mut Painter, style: GridLineStyle, start: [f32; 2], end: [f32; 2]) {
+        painter.draw_lines(
+            JointStyle::Bevel,
+            EndCapStyle::Butt,
+            LineStripStyle::Open,
+            style.color,
+            style.thickness,
+            &[start, end]
+        );
+    }
+
+    fn draw_labels(&self, camera: &EditorCamera, from: [f32; 2], to: [f32; 2], major_step: f32) {
+        let padding = self.label_size / 4.0;
+        let corner = camera.world_to_screen(from);
+        for line in grid_lines(self.min[0], self.max[0], major_step, self.spacing, from[0], to[0]) {
+            let screen_x = camera.world_to_screen([line.coord, 0.0])[0];
+            draw_text(
+                &line.index.to_string(),
+                screen_x + padding,
+                corner[1] + self.label_size,
+                self.label_size,
+                self.label_color
+            );
+        }
+        for line in grid_lines(self.min[1], self.max[1], major_step, self.spacing, from[1], to[1]) {
+            let screen_y = camera.world_to_screen([0.0, line.coord])[1];
+            draw_text(
+                &line.index.to_string(),
+                corner[0] + padding,
+                screen_y + self.label_size,
+                self.label_size,
+                self.label_color
+            );
+        }
+    }
+}
+
+fn with_offset(dash: DashStyle, offset: f32) -> DashStyle {
+    match dash {
+        DashStyle::Solid => DashStyle::Solid,
+        DashStyle::Dashed { dash, gap, .. } => DashStyle::Dashed { dash, gap, offset }
+    }
+}
+
+/// Lines every `step` from `start` to `end` which fall into `visible_from..=visible_to`.
+/// `spacing` is the minor step the line indices are counted in
+pub fn grid_lines(
+    start: f32,
+    end: f32,
+    step: f32,
+    spacing: f32,
+    visible_from: f32,
+    visible_to: f32
+) -> impl Iterator<Item = GridLine> {
+    let from = visible_from.max(start);
+    let to = visible_to.min(end);
+    let first = ((from - start) / step).ceil().max(0.0) as usize;
+    let last = if to < from { 0 } else { ((to - start) / step + 0.0001).floor() as usize + 1 };
+    let minor_per_step = (step / spacing).round() as usize;
+    (first..last.max(first)).map(move |id| GridLine {
+        index: id * minor_per_step,
+        coord: start + id as f32 * step
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::*;
+    use crate::editor::grid_overlay::{grid_lines, GridLine, GridLineStyle, GridOverlay};
+    use crate::poly_line_2d::style::DashStyle;
+
+    fn overlay() -> GridOverlay {
+        let style = GridLineStyle { color: WHITE, thickness: 1.0, dash: DashStyle::Solid };
+        GridOverlay {
+            min: [0.0, 0.0],
+            max: [1000.0, 500.0],
+            spacing: 10.0,
+            major_every: 5,
+            min_pixel_spacing: 8.0,
+            minor: style,
+            major: style,
+            label_size: 0.0,
+            label_color: WHITE
+        }
+    }
+
+    #[test]
+    pub fn test_line_steps_adapt_to_zoom() {
+        let overlay = overlay();
+        assert_eq!((Some(10.0), 50.0), overlay.line_steps(1.0));
+        assert_eq!((None, 50.0), overlay.line_steps(0.5));
+        assert_eq!((None, 100.0), overlay.line_steps(0.1));
+        assert_eq!((None, 400.0), overlay.line_steps(0.025));
+    }
+
+    #[test]
+    pub fn test_lines_are_clipped_to_bounds_and_view() {
+        let lines = grid_lines(0.0, 100.0, 20.0, 10.0, 15.0, 65.0).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                GridLine { index: 2, coord: 20.0 },
+                GridLine { index: 4, coord: 40.0 },
+                GridLine { index: 6, coord: 60.0 }
+            ],
+            lines
+        );
+        assert_eq!(6, grid_lines(0.0, 100.0, 20.0, 10.0, -50.0, 500.0).count());
+        assert_eq!(0, grid_lines(0.0, 100.0, 20.0, 10.0, 120.0, 500.0).count());
+        assert_eq!(
+            vec![GridLine { index: 0, coord: -5.0 }, GridLine { index: 1, coord: 5.0 }],
+            grid_lines(-5.0, 5.0, 10.0, 10.0, -100.0, 100.0).collect::<Vec<_>>()
+        );
+    }
+}
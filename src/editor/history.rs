@@ -0,0 +1,322 @@
+use std::collections::VecDeque;
+use std::mem::size_of;
+use crate::terrain::WeightGrid;
+
+/// Weights stored run-length encoded: consecutive values with the same bits are kept once with a repeat count
+#[derive(Default)]
+struct PackedWeights {
+    repeats: Vec<(f32, u32)>
+}
+
+impl PackedWeights {
+    fn push(&mut self, value: f32) {
+        match self.repeats.last_mut() {
+            Some((last, count)) if last.to_bits() == value.to_bits() => *count += 1,
+            _ => self.repeats.push((value, 1))
+        }
+    }
+
+    fn values(&self) -> impl Iterator<Item = f32> + '_ {
+        self.repeats.iter().flat_map(|&(value, count)| std::iter::repeat_n(value, count as usize))
+    }
+}
+
+/// Changes made to a grid by a single edit. Only the touched vertices are stored: their indices as runs
+/// of consecutive vertices, their old and new weights run-length encoded in the order of the runs
+pub struct GridDelta {
+    /// Start and length of each run of changed vertices
+    runs: Vec<(usize, usize)>,
+    before: PackedWeights,
+    after: PackedWeights
+}
+
+impl GridDelta {
+    /// Compares two snapshots of the same grid bit by bit and keeps only the changed vertices
+    pub fn between(before: &[f32], after: &[f32]) -> Self {
+        let mut delta = Self { runs: Vec::new(), before: PackedWeights::default(), after: PackedWeights::default() };
+        for (id, (old, new)) in before.iter().zip(after.iter()).enumerate() {
+            if old.to_bits() == new.to_bits() {
+                continue;
+            }
+            match delta.runs.last_mut() {
+                Some((start, length)) if *start + *length == id => *length += 1,
+                _ => delta.runs.push((id, 1))
+            }
+            delta.before.push(*old);
+            delta.after.push(*new);
+        }
+        delta.runs.shrink_to_fit();
+        delta.before.repeats.shrink_to_fit();
+        delta.after.repeats.shrink_to_fit();
+        delta
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Approximate amount of memory taken by the delta in bytes
+    pub fn memory_size(&self) -> usize {
+        size_of::<Self>()
+            + self.runs.capacity() * size_of::<(usize, usize)>()
+            + (self.before.repeats.capacity() + self.after.repeats.capacity()) * size_of::<(f32, u32)>()
+    }
+
+    fn write(&self, grid: &mut WeightGrid, values: &PackedWeights) {
+        let weights = grid.weights_mut();
+        let targets = self.runs.iter().flat_map(|&(start, length)| start..start + length);
+        for (id, value) in targets.zip(values.values()) {
+            weights[id] = value;
+        }
+    }
+
+    fn revert(&self, grid: &mut WeightGrid) {
+        self.write(grid, &self.before);
+    }
+
+    fn apply(&self, grid: &mut WeightGrid) {
+        self.write(grid, &self.after);
+    }
+}
+
+/// Undo/redo history of grid edits. An edit spans from `begin_edit` to `end_edit`,
+/// e.g. from mouse-down to mouse-up of a brush stroke
+pub struct EditHistory {
+    undo_stack: VecDeque<GridDelta>,
+    redo_stack: Vec<GridDelta>,
+    memory_budget: usize,
+    used_memory: usize,
+    snapshot: Vec<f32>,
+    recording: bool
+}
+
+impl EditHistory {
+    /// `memory_budget` is the amount of bytes the stored deltas may take;
+    /// the oldest edits are forgotten once it is exceeded
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            memory_budget,
+            used_memory: 0,
+            snapshot: Vec::new(),
+            recording: false
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn used_memory(&self) -> usize {
+        self.used_memory
+    }
+
+    pub fn begin_edit(&mut self, grid: &WeightGrid) {
+        self.snapshot.clear();
+        self.snapshot.extend_from_slice(grid.weights());
+        self.recording = true;
+    }
+
+    /// Finishes the edit started with `begin_edit`. Returns `false` if nothing was changed
+    pub fn end_edit(&mut self, grid: &WeightGrid) -> bool {
+        if !self.recording {
+            return false;
+        }
+        self.recording = false;
+        let delta = GridDelta::between(&self.snapshot, grid.weights());
+        if delta.is_empty() {
+            return false;
+        }
+        self.push(delta);
+        true
+    }
+
+    pub fn push(&mut self, delta: GridDelta) {
+        for dropped in self.redo_stack.drain(..) {
+            self.used_memory -= dropped.memory_size();
+        }
+        self.used_memory += delta.memory_size();
+        self.undo_stack.push_back(delta);
+        // the latest edit is always kept even if it alone exceeds the budget
+        while self.used_memory > self.memory_budget && self.undo_stack.len() > 1 {
+            let dropped = self.undo_stack.pop_front().unwrap();
+            self.used_memory -= dropped.memory_size();
+        }
+    }
+
+    /// Reverts the latest edit. An edit still being recorded is finished first, so it's the one reverted
+    pub fn undo(&mut self, grid: &mut WeightGrid) -> bool {
+        if self.recording {
+            self.end_edit(grid);
+        }
+        match self.undo_stack.pop_back() {
+            None => false,
+            Some(delta) => {
+                delta.revert(grid);
+                self.redo_stack.push(delta);
+                true
+            }
+        }
+    }
+
+    /// Applies the latest undone edit again. Like in `undo`, an edit still being recorded is finished first;
+    /// if it changed the grid it clears the redo stack and there is nothing left to redo
+    pub fn redo(&mut self, grid: &mut WeightGrid) -> bool {
+        if self.recording {
+            self.end_edit(grid);
+        }
+        match self.redo_stack.pop() {
+            None => false,
+            Some(delta) => {
+                delta.apply(grid);
+                self.undo_stack.push_back(delta);
+                true
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.used_memory = 0;
+        self.recording = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::history::{EditHistory, GridDelta};
+    use crate::terrain::WeightGrid;
+
+    fn bits(grid: &WeightGrid) -> Vec<u32> {
+        grid.weights().iter().map(|it| it.to_bits()).collect()
+    }
+
+    fn stroke(history: &mut EditHistory, grid: &mut WeightGrid, x: usize, value: f32) {
+        history.begin_edit(grid);
+        for y in 0..grid.height() {
+            grid.set(x, y, value);
+            grid.set((x + 1) % grid.width(), y, value * 0.3);
+        }
+        history.end_edit(grid);
+    }
+
+    #[test]
+    pub fn test_undo_redo_restore_identical_grids() {
+        let mut grid = WeightGrid::new(16, 16, 1.0, 0.001);
+        let mut history = EditHistory::new(1 << 20);
+
+        let mut states = vec![bits(&grid)];
+        for (x, value) in [(1, 0.7), (4, 0.123_456_7), (1, 0.2), (15, 1.0)] {
+            stroke(&mut history, &mut grid, x, value);
+            states.push(bits(&grid));
+        }
+
+        for state in states.iter().rev().skip(1) {
+            assert!(history.undo(&mut grid));
+            assert_eq!(*state, bits(&grid));
+        }
+        assert!(!history.undo(&mut grid));
+
+        for state in states.iter().skip(1) {
+            assert!(history.redo(&mut grid));
+            assert_eq!(*state, bits(&grid));
+        }
+        assert!(!history.redo(&mut grid));
+
+        history.undo(&mut grid);
+        history.undo(&mut grid);
+        stroke(&mut history, &mut grid, 8, 0.5);
+        assert!(!history.can_redo());
+        history.undo(&mut grid);
+        assert_eq!(states[2], bits(&grid));
+    }
+
+    #[test]
+    pub fn test_unchanged_edit_is_not_recorded() {
+        let mut grid = WeightGrid::new(4, 4, 1.0, 0.001);
+        let mut history = EditHistory::new(1 << 20);
+        history.begin_edit(&grid);
+        grid.set(1, 1, 0.0);
+        assert!(!history.end_edit(&grid));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    pub fn test_memory_budget_drops_oldest_edits() {
+        let mut grid = WeightGrid::new(64, 64, 1.0, 0.001);
+        let mut history = EditHistory::new(16 * 1024);
+        for x in 0..10 {
+            stroke(&mut history, &mut grid, x, 0.9);
+        }
+        assert!(history.used_memory() <= 16 * 1024);
+
+        let mut undone = 0;
+        while history.undo(&mut grid) {
+            undone += 1;
+        }
+        assert!(undone > 0 && undone < 10);
+    }
+
+    #[test]
+    pub fn test_deltas_are_run_length_encoded() {
+        let mut grid = WeightGrid::new(64, 64, 1.0, 0.001);
+        let before = grid.weights().to_vec();
+        for y in 0..64 {
+            for x in 16..48 {
+                grid.set(x, y, 1.0);
+            }
+        }
+        grid.set(20, 20, 0.5);
+        let delta = GridDelta::between(&before, grid.weights());
+        // 64 runs of 32 vertices, all of them zero before and one after except for the odd vertex
+        let uncompressed = 64 * 32 * 2 * std::mem::size_of::<f32>();
+        assert!(delta.memory_size() * 8 < uncompressed, "{} bytes", delta.memory_size());
+
+        let mut restored = WeightGrid::new(64, 64, 1.0, 0.001);
+        restored.weights_mut().copy_from_slice(&before);
+        delta.apply(&mut restored);
+        assert_eq!(bits(&grid), bits(&restored));
+        delta.revert(&mut restored);
+        assert!(restored.weights().iter().zip(before.iter()).all(|(a, b)| a.to_bits() == b.to_bits()));
+    }
+
+    #[test]
+    pub fn test_undo_and_redo_finish_the_edit_in_progress() {
+        let mut grid = WeightGrid::new(8, 8, 1.0, 0.001);
+        let mut history = EditHistory::new(1 << 20);
+        stroke(&mut history, &mut grid, 2, 0.8);
+        let painted = bits(&grid);
+        history.undo(&mut grid);
+
+        // an unchanged edit in progress leaves the redo stack alone
+        history.begin_edit(&grid);
+        assert!(history.redo(&mut grid));
+        assert!(!history.is_recording());
+        assert_eq!(painted, bits(&grid));
+
+        history.begin_edit(&grid);
+        grid.set(5, 5, 0.4);
+        let edited = bits(&grid);
+        assert!(history.undo(&mut grid));
+        assert!(!history.is_recording());
+        assert_eq!(painted, bits(&grid));
+
+        history.begin_edit(&grid);
+        grid.set(6, 6, 0.4);
+        assert!(!history.redo(&mut grid));
+        assert!(!history.is_recording());
+        assert_ne!(edited, bits(&grid));
+        assert!(history.undo(&mut grid));
+        assert_eq!(painted, bits(&grid));
+    }
+}
@@ -0,0 +1,41 @@
+use macroquad::prelude::*;
+
+/// Lines of text on a translucent box in screen space
+pub struct InfoPanel {
+    /// Screen-space top left corner
+    pub position: [f32; 2],
+    pub font_size: f32,
+    pub padding: f32,
+    pub text_color: Color,
+    pub background: Color
+}
+
+impl InfoPanel {
+    pub fn draw(&self, lines: &[String]) {
+        if lines.is_empty() {
+            return;
+        }
+        let width = lines
+            .iter()
+            .map(|it| measure_text(it, None, self.font_size as u16, 1.0).width)
+            .fold(0.0, f32::max);
+        let line_height = self.font_size;
+        draw_rectangle(
+            self.position[0],
+            self.position[1],
+            width + self.padding * 2.0,
+            line_height * lines.len() as f32 + self.padding * 2.0,
+            self.background
+        );
+        for (id, line) in lines.iter().enumerate() {
+            draw_text(
+                line,
+                self.position[0] + self.padding,
+                // draw_text places the baseline, which sits roughly three quarters down the line
+                self.position[1] + self.padding + line_height * (id as f32 + 0.75),
+                self.font_size,
+                self.text_color
+            );
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod history;
+pub mod brush;
+pub mod operation;
+pub mod camera;
+pub mod grid_overlay;
+pub mod info_panel;
@@ -0,0 +1,220 @@
+use std::ops::Range;
+use crate::terrain::WeightGrid;
+
+const MIN_STAMP_SPACING: f32 = 0.01;
+
+/// What a brush stamp does with the weights under it
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BrushOperation {
+    Add,
+    Subtract,
+    /// Blurs weights with their neighbours
+    Smooth,
+    /// Pulls weights towards the value under the cursor at the start of the stroke
+    Flatten,
+    /// Pulls weights towards the given value
+    Set(f32),
+    /// Adds random noise
+    Noise
+}
+
+impl BrushOperation {
+    pub fn next(self) -> Self {
+        match self {
+            BrushOperation::Add => BrushOperation::Subtract,
+            BrushOperation::Subtract => BrushOperation::Smooth,
+            BrushOperation::Smooth => BrushOperation::Flatten,
+            BrushOperation::Flatten => BrushOperation::Set(0.5),
+            BrushOperation::Set(_) => BrushOperation::Noise,
+            BrushOperation::Noise => BrushOperation::Add
+        }
+    }
+}
+
+/// Per-stroke state shared by all stamps of the stroke
+#[derive(Copy, Clone, Debug)]
+pub struct StrokeState {
+    pub operation: BrushOperation,
+    pub flatten_target: f32,
+    last_position: [f32; 2],
+    distance_to_next_stamp: f32
+}
+
+impl StrokeState {
+    /// Starts a stroke at `world_position` capturing the weight of the nearest vertex as the flatten target
+    pub fn begin(grid: &WeightGrid, operation: BrushOperation, world_position: [f32; 2]) -> Self {
+        let to_vertex = |coord: f32, count: usize| {
+            ((coord / grid.cell_size()).round().max(0.0) as usize).min(count.saturating_sub(1))
+        };
+        let x = to_vertex(world_position[0], grid.width());
+        let y = to_vertex(world_position[1], grid.height());
+        Self {
+            operation,
+            flatten_target: grid.get(x, y),
+            last_position: world_position,
+            distance_to_next_stamp: 0.0
+        }
+    }
+
+    /// Moves the brush to `world_position` and returns the stamp positions placed every `spacing`
+    /// world units along the way. The first call after `begin` stamps at the stroke start.
+    /// Stamps depend only on the path, not on how many frames it was split into
+    pub fn advance(&mut self, world_position: [f32; 2], spacing: f32) -> Vec<[f32; 2]> {
+        let spacing = spacing.max(MIN_STAMP_SPACING);
+        let delta = [
+            world_position[0] - self.last_position[0],
+            world_position[1] - self.last_position[1]
+        ];
+        let length = (delta[0] * delta[0] + delta[1] * delta[1]).sqrt();
+
+        let mut stamps = Vec::new();
+        let mut travelled = self.distance_to_next_stamp;
+        while travelled <= length {
+            let t = if length > 0.0 { travelled / length } else { 0.0 };
+            stamps.push([
+                self.last_position[0] + delta[0] * t,
+                self.last_position[1] + delta[1] * t
+            ]);
+            travelled += spacing;
+        }
+        self.distance_to_next_stamp = travelled - length;
+        self.last_position = world_position;
+        stamps
+    }
+}
+
+/// Applies one brush stamp to the vertices in `xs` x `ys`.
+/// `influence` gives the brush falloff at the world position of a vertex and `amount` is the strength of the stamp,
+/// `noise` yields random values in `-1.0..=1.0`
+pub fn apply_operation(
+    grid: &mut WeightGrid,
+    xs: Range<usize>,
+    ys: Range<usize>,
+    stroke: &StrokeState,
+    amount: f32,
+    influence: impl Fn([f32; 2]) -> f32,
+    mut noise: impl FnMut() -> f32
+) {
+    let xs = xs.start.min(grid.width())..xs.end.min(grid.width());
+    let ys = ys.start.min(grid.height())..ys.end.min(grid.height());
+    let mut updates = Vec::new();
+
+    for y in ys.clone() {
+        for x in xs.clone() {
+            let power = influence(grid.vertex_position(x, y));
+            if power <= 0.0 {
+                continue;
+            }
+            let weight = grid.get(x, y);
+            let blend = (power * amount).clamp(0.0, 1.0);
+            let new_weight = match stroke.operation {
+                BrushOperation::Add => weight + power * amount,
+                BrushOperation::Subtract => weight - power * amount,
+                BrushOperation::Smooth => weight + (neighbourhood_average(grid, x, y) - weight) * blend,
+                BrushOperation::Flatten => weight + (stroke.flatten_target - weight) * blend,
+                BrushOperation::Set(value) => weight + (value - weight) * blend,
+                BrushOperation::Noise => weight + noise() * power * amount
+            };
+            updates.push((x, y, new_weight.clamp(0.0, 1.0)));
+        }
+    }
+
+    // written afterwards so smoothing reads only the weights from before the stamp
+    for (x, y, weight) in updates {
+        grid.set(x, y, weight);
+    }
+}
+
+fn neighbourhood_average(grid: &WeightGrid, x: usize, y: usize) -> f32 {
+    let mut sum = 0.0;
+    let mut count = 0;
+    for ny in y.saturating_sub(1)..(y + 2).min(grid.height()) {
+        for nx in x.saturating_sub(1)..(x + 2).min(grid.width()) {
+            sum += grid.get(nx, ny);
+            count += 1;
+        }
+    }
+    sum / count as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::operation::{apply_operation, BrushOperation, StrokeState};
+    use crate::terrain::WeightGrid;
+
+    fn stamp(grid: &mut WeightGrid, operation: BrushOperation, flatten_target: f32, amount: f32) {
+        let (width, height) = (grid.width(), grid.height());
+        let mut stroke = StrokeState::begin(grid, operation, [0.0, 0.0]);
+        stroke.flatten_target = flatten_target;
+        apply_operation(grid, 0..width, 0..height, &stroke, amount, |_| 1.0, || 0.5);
+    }
+
+    #[test]
+    pub fn test_smooth_preserves_constant_field() {
+        let mut grid = WeightGrid::new(5, 5, 1.0, 0.001);
+        grid.weights_mut().iter_mut().for_each(|it| *it = 0.25);
+        stamp(&mut grid, BrushOperation::Smooth, 0.0, 1.0);
+        assert!(grid.weights().iter().all(|it| (*it - 0.25).abs() < 0.0001));
+
+        grid.set(2, 2, 1.0);
+        stamp(&mut grid, BrushOperation::Smooth, 0.0, 1.0);
+        assert!(grid.get(2, 2) < 1.0);
+        assert!(grid.get(1, 1) > 0.25);
+    }
+
+    #[test]
+    pub fn test_flatten_and_set_pull_towards_target() {
+        let mut grid = WeightGrid::new(3, 3, 10.0, 0.001);
+        grid.set(0, 0, 0.8);
+        let stroke = StrokeState::begin(&grid, BrushOperation::Flatten, [2.0, 3.0]);
+        assert_eq!(0.8, stroke.flatten_target);
+
+        stamp(&mut grid, BrushOperation::Flatten, stroke.flatten_target, 1.0);
+        assert!(grid.weights().iter().all(|it| (*it - 0.8).abs() < 0.0001));
+
+        stamp(&mut grid, BrushOperation::Set(0.2), 0.0, 0.5);
+        assert!(grid.weights().iter().all(|it| (*it - 0.5).abs() < 0.0001));
+    }
+
+    #[test]
+    pub fn test_stamps_do_not_depend_on_frame_rate() {
+        let grid = WeightGrid::new(3, 3, 10.0, 0.001);
+        let path = |t: f32| [t * 100.0, (t * 6.0).sin() * 30.0];
+
+        let mut stamps_per_rate = Vec::new();
+        for frames in [30, 144] {
+            let mut stroke = StrokeState::begin(&grid, BrushOperation::Add, path(0.0));
+            let mut stamps = Vec::new();
+            for frame in 0..=frames {
+                stamps.extend(stroke.advance(path(frame as f32 / frames as f32), 5.0));
+            }
+            stamps_per_rate.push(stamps);
+        }
+
+        let (slow, fast) = (&stamps_per_rate[0], &stamps_per_rate[1]);
+        assert_eq!([0.0, 0.0], slow[0]);
+        // the sampled path differs a bit between the frame rates, the stamp count should not
+        assert!((slow.len() as isize - fast.len() as isize).abs() <= 1);
+        for (a, b) in slow.iter().zip(fast.iter()).take(8) {
+            assert!((a[0] - b[0]).abs() < 1.0 && (a[1] - b[1]).abs() < 1.0);
+        }
+
+        let mut stroke = StrokeState::begin(&grid, BrushOperation::Add, [0.0, 0.0]);
+        assert_eq!(1, stroke.advance([0.0, 0.0], 5.0).len());
+        assert!(stroke.advance([0.0, 0.0], 5.0).is_empty());
+        let stamps = stroke.advance([12.0, 0.0], 5.0);
+        assert_eq!(vec![[5.0, 0.0], [10.0, 0.0]], stamps);
+        assert_eq!(vec![[15.0, 0.0]], stroke.advance([16.0, 0.0], 5.0));
+    }
+
+    #[test]
+    pub fn test_add_subtract_noise_are_clamped() {
+        let mut grid = WeightGrid::new(3, 3, 1.0, 0.001);
+        stamp(&mut grid, BrushOperation::Add, 0.0, 4.0);
+        assert!(grid.weights().iter().all(|it| *it == 1.0));
+        stamp(&mut grid, BrushOperation::Subtract, 0.0, 0.25);
+        assert!(grid.weights().iter().all(|it| *it == 0.75));
+        stamp(&mut grid, BrushOperation::Noise, 0.0, 2.0);
+        assert!(grid.weights().iter().all(|it| *it == 1.0));
+    }
+}
@@ -0,0 +1,287 @@
+use crate::terrain::WeightGrid;
+use crate::generation::random::Random;
+use crate::generation::landscape::write_density;
+
+/// Cellular-automata cave generator working on the grid vertices
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CaveSettings {
+    pub seed: u64,
+    /// Chance of a vertex starting as a wall
+    pub fill_probability: f32,
+    pub iterations: usize,
+    /// An open vertex turns into a wall with at least this many wall neighbours out of eight
+    pub birth_limit: usize,
+    /// A wall stays with at least this many wall neighbours
+    pub survival_limit: usize,
+    /// Open pockets with fewer vertices get filled
+    pub min_open_region: usize,
+    /// Wall islands with fewer vertices get removed
+    pub min_wall_region: usize,
+    /// Box blur passes over the walls before the contour is extracted
+    pub blur_passes: usize
+}
+
+impl Default for CaveSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            fill_probability: 0.45,
+            iterations: 5,
+            birth_limit: 5,
+            survival_limit: 4,
+            min_open_region: 16,
+            min_wall_region: 8,
+            blur_passes: 1
+        }
+    }
+}
+
+/// Boolean map of grid vertices, `true` for walls
+struct WallMap {
+    width: usize,
+    height: usize,
+    walls: Vec<bool>
+}
+
+impl WallMap {
+    fn is_wall(&self, x: isize, y: isize) -> bool {
+        // outside of the map counts as a wall, so caves stay closed
+        if x < 0 || y < 0 || x >= self.width as isize || y >= self.height as isize {
+            return true;
+        }
+        self.walls[y as usize * self.width + x as usize]
+    }
+
+    fn wall_neighbours(&self, x: usize, y: usize) -> usize {
+        let (x, y) = (x as isize, y as isize);
+        let mut count = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if (dx != 0 || dy != 0) && self.is_wall(x + dx, y + dy) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    fn step(&mut self, birth_limit: usize, survival_limit: usize) {
+        let walls = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let neighbours = self.wall_neighbours(x, y);
+                if self.walls[y * self.width + x] {
+                    neighbours >= survival_limit
+                } else {
+                    neighbours >= birth_limit
+                }
+            })
+            .collect();
+        self.walls = walls;
+    }
+
+    /// 4-connected regions of vertices equal to `wall`, as lists of vertex indices
+    fn regions(&self, wall: bool) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.walls.len()];
+        let mut regions = Vec::new();
+        for id in 0..self.walls.len() {
+            if visited[id] || self.walls[id] != wall {
+                continue;
+            }
+            let mut region = Vec::new();
+            let mut stack = vec![id];
+            visited[id] = true;
+            while let Some(current) = stack.pop() {
+                region.push(current);
+                let (x, y) = (current % self.width, current / self.width);
+                let neighbours = [
+                    (x > 0).then(|| current - 1),
+                    (x + 1 < self.width).then(|| current + 1),
+                    (y > 0).then(|| current - self.width),
+                    (y + 1 < self.height).then(|| current + self.width)
+                ];
+                for neighbour in neighbours.iter().flatten() {
+                    if !visited[*neighbour] && self.walls[*neighbour] == wall {
+                        visited[*neighbour] = true;
+                        stack.push(*neighbour);
+                    }
+                }
+            }
+            regions.push(region);
+        }
+        regions
+    }
+
+    /// Flips the regions of `wall` vertices smaller than `min_size`
+    fn remove_small_regions(&mut self, wall: bool, min_size: usize) {
+        for region in self.regions(wall) {
+            if region.len() < min_size {
+                region.iter().for_each(|it| self.walls[*it] = !wall);
+            }
+        }
+    }
+
+    /// Carves corridors from the largest open region to the closest other one until all are connected
+    fn connect_regions(&mut self) {
+        loop {
+            let mut regions = self.regions(false);
+            if regions.len() <= 1 {
+                return;
+            }
+            regions.sort_by_key(|it| std::cmp::Reverse(it.len()));
+            let is_edge = |id: &usize| {
+                let (x, y) = ((id % self.width) as isize, (id / self.width) as isize);
+                self.is_wall(x + 1, y) || self.is_wall(x - 1, y) || self.is_wall(x, y + 1) || self.is_wall(x, y - 1)
+            };
+            let main = regions[0].iter().copied().filter(is_edge).collect::<Vec<_>>();
+            let others = regions[1..].iter().flatten().copied().filter(is_edge).collect::<Vec<_>>();
+
+            let mut closest = (usize::MAX, 0, 0);
+            for &a in main.iter() {
+                let (ax, ay) = ((a % self.width) as isize, (a / self.width) as isize);
+                for &b in others.iter() {
+                    let (bx, by) = ((b % self.width) as isize, (b / self.width) as isize);
+                    let distance = ((ax - bx) * (ax - bx) + (ay - by) * (ay - by)) as usize;
+                    if distance < closest.0 {
+                        closest = (distance, a, b);
+                    }
+                }
+            }
+            self.carve_corridor(closest.1, closest.2);
+        }
+    }
+
+    /// Opens a three vertices wide straight corridor between two vertices
+    fn carve_corridor(&mut self, from: usize, to: usize) {
+        let (fx, fy) = ((from % self.width) as f32, (from / self.width) as f32);
+        let (tx, ty) = ((to % self.width) as f32, (to / self.width) as f32);
+        let steps = (tx - fx).abs().max((ty - fy).abs()).ceil() as usize;
+        for step in 0..=steps {
+            let t = if steps == 0 { 0.0 } else { step as f32 / steps as f32 };
+            let (x, y) = ((fx + (tx - fx) * t).round() as isize, (fy + (ty - fy) * t).round() as isize);
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (cx, cy) = (x + dx, y + dy);
+                    if cx >= 0 && cy >= 0 && cx < self.width as isize && cy < self.height as isize {
+                        self.walls[cy as usize * self.width + cx as usize] = false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Walls as densities of one, smoothed by 3x3 box blurs
+    fn blurred_density(&self, passes: usize) -> Vec<f32> {
+        let mut density = self.walls.iter().map(|it| if *it { 1.0 } else { 0.0 }).collect::<Vec<f32>>();
+        for _ in 0..passes {
+            let mut blurred = vec![0.0; density.len()];
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let mut sum = 0.0;
+                    for dy in -1..=1isize {
+                        for dx in -1..=1isize {
+                            let (nx, ny) = (x as isize + dx, y as isize + dy);
+                            sum += if nx < 0 || ny < 0 || nx >= self.width as isize || ny >= self.height as isize {
+                                1.0
+                            } else {
+                                density[ny as usize * self.width + nx as usize]
+                            };
+                        }
+                    }
+                    blurred[y * self.width + x] = sum / 9.0;
+                }
+            }
+            density = blurred;
+        }
+        density
+    }
+}
+
+impl CaveSettings {
+    /// Wall flags of a `width` x `height` vertex map before blurring, row by row
+    pub fn walls(&self, width: usize, height: usize) -> Vec<bool> {
+        self.wall_map(width, height).walls
+    }
+
+    fn wall_map(&self, width: usize, height: usize) -> WallMap {
+        let mut random = Random::new(self.seed);
+        let mut map = WallMap {
+            width,
+            height,
+            walls: (0..width * height).map(|_| random.chance(self.fill_probability)).collect()
+        };
+        for _ in 0..self.iterations {
+            map.step(self.birth_limit, self.survival_limit);
+        }
+        map.remove_small_regions(false, self.min_open_region);
+        map.remove_small_regions(true, self.min_wall_region);
+        map.connect_regions();
+        map
+    }
+
+    /// Overwrites all the weights of `grid` with a cave whose open space is a single connected region
+    pub fn generate(&self, grid: &mut WeightGrid) {
+        let map = self.wall_map(grid.width(), grid.height());
+        let mut density = map.blurred_density(self.blur_passes);
+
+        // blurring narrows corridors and can cut them, so the thresholded walls are cleaned up and connected
+        // again and the vertices which changed sides get a density on their new side
+        let blurred_walls = density.iter().map(|it| *it >= 0.5).collect::<Vec<_>>();
+        let mut walls = WallMap { width: map.width, height: map.height, walls: blurred_walls.clone() };
+        walls.remove_small_regions(false, self.min_open_region);
+        walls.connect_regions();
+        for ((value, before), after) in density.iter_mut().zip(blurred_walls).zip(walls.walls) {
+            if before != after {
+                *value = if after { 1.0 } else { 0.0 };
+            }
+        }
+        write_density(grid, &density, 0.5);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::WeightGrid;
+    use crate::generation::caves::{CaveSettings, WallMap};
+
+    fn open_regions(grid: &WeightGrid) -> usize {
+        let map = WallMap {
+            width: grid.width(),
+            height: grid.height(),
+            walls: (0..grid.height())
+                .flat_map(|y| (0..grid.width()).map(move |x| (x, y)))
+                .map(|(x, y)| grid.is_solid(x, y))
+                .collect()
+        };
+        map.regions(false).len()
+    }
+
+    #[test]
+    pub fn test_cave_is_connected_and_deterministic() {
+        // wider blurs cut narrow corridors of the automaton output
+        for (seed, blur_passes) in (0..40).flat_map(|seed| (1..=4).map(move |passes| (seed, passes))) {
+            let settings = CaveSettings { seed, blur_passes, ..CaveSettings::default() };
+            let mut grid = WeightGrid::new(64, 48, 1.0, 0.001);
+            settings.generate(&mut grid);
+            assert_eq!(1, open_regions(&grid), "seed {} with {} blur passes", seed, blur_passes);
+            let open = grid.weights().iter().filter(|it| **it < 0.001).count();
+            assert!(open > 64 * 48 / 4);
+
+            let mut again = WeightGrid::new(64, 48, 1.0, 0.001);
+            settings.generate(&mut again);
+            assert_eq!(grid.weights(), again.weights());
+        }
+        let a = CaveSettings { seed: 1, ..CaveSettings::default() }.walls(32, 32);
+        let b = CaveSettings { seed: 2, ..CaveSettings::default() }.walls(32, 32);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    pub fn test_small_regions_are_removed() {
+        let settings = CaveSettings { seed: 5, min_open_region: 40, min_wall_region: 40, ..CaveSettings::default() };
+        let walls = settings.walls(48, 48);
+        let map = WallMap { width: 48, height: 48, walls };
+        assert_eq!(1, map.regions(false).len());
+        assert!(map.regions(true).iter().all(|it| it.len() >= 40));
+    }
+}
@@ -0,0 +1,206 @@
+use crate::terrain::WeightGrid;
+use crate::generation::random::Random;
+use crate::generation::noise::{PerlinNoise, Fbm};
+
+/// Offsets the sample points by another pair of noise fields for twisted, less blobby shapes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DomainWarp {
+    /// Largest offset in world units
+    pub amplitude: f32,
+    pub fbm: Fbm
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FalloffShape {
+    Circle,
+    Square
+}
+
+/// Pushes the density down towards the map border so the land ends up as an island
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IslandFalloff {
+    pub shape: FalloffShape,
+    /// Distance from the centre, as a fraction of the half size of the map, where the falloff begins
+    pub start: f32,
+    /// Distance where nothing is left of the noise
+    pub end: f32
+}
+
+impl IslandFalloff {
+    /// Zero in the middle of the map rising to one at `end`
+    pub fn mask(&self, grid: &WeightGrid, point: [f32; 2]) -> f32 {
+        let cells = grid.cell_rect();
+        let half_size = [
+            (cells.width as f32 * grid.cell_size() / 2.0).max(f32::EPSILON),
+            (cells.height as f32 * grid.cell_size() / 2.0).max(f32::EPSILON)
+        ];
+        let dx = (point[0] - half_size[0]) / half_size[0];
+        let dy = (point[1] - half_size[1]) / half_size[1];
+        let distance = match self.shape {
+            FalloffShape::Circle => (dx * dx + dy * dy).sqrt(),
+            FalloffShape::Square => dx.abs().max(dy.abs())
+        };
+        let t = ((distance - self.start) / (self.end - self.start).max(f32::EPSILON)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// Terrain from thresholded fractal noise. The same settings and seed always give the same map
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NoiseTerrain {
+    pub seed: u64,
+    pub fbm: Fbm,
+    pub warp: Option<DomainWarp>,
+    pub falloff: Option<IslandFalloff>,
+    /// Density in `[-1, 1]` above which the terrain is solid
+    pub threshold: f32
+}
+
+impl Default for NoiseTerrain {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            fbm: Fbm::default(),
+            warp: None,
+            falloff: None,
+            threshold: 0.0
+        }
+    }
+}
+
+impl NoiseTerrain {
+    /// Density at every grid vertex, row by row
+    pub fn density(&self, grid: &WeightGrid) -> Vec<f32> {
+        let mut random = Random::new(self.seed);
+        let noise = PerlinNoise::new(random.next_u64());
+        let warp_noises = [PerlinNoise::new(random.next_u64()), PerlinNoise::new(random.next_u64())];
+
+        let mut density = Vec::with_capacity(grid.width() * grid.height());
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                let position = grid.vertex_position(x, y);
+                let point = match self.warp {
+                    None => position,
+                    Some(warp) => [
+                        position[0] + warp.amplitude * warp.fbm.sample(&warp_noises[0], position),
+                        position[1] + warp.amplitude * warp.fbm.sample(&warp_noises[1], position)
+                    ]
+                };
+                let value = self.fbm.sample(&noise, point);
+                density.push(match self.falloff {
+                    None => value,
+                    Some(falloff) => {
+                        let mask = falloff.mask(grid, position);
+                        value + (-1.0 - value) * mask
+                    }
+                });
+            }
+        }
+        density
+    }
+
+    /// Overwrites all the weights of `grid`
+    pub fn generate(&self, grid: &mut WeightGrid) {
+        let density = self.density(grid);
+        write_density(grid, &density, self.threshold);
+    }
+}
+
+/// Turns a density field sampled at the grid vertices into weights: vertices at or above `threshold`
+/// become solid, with weights estimated from the distance to the threshold crossing
+/// so the contour follows the density surface between the vertices
+pub fn write_density(grid: &mut WeightGrid, density: &[f32], threshold: f32) {
+    let (width, height) = (grid.width(), grid.height());
+    let cell_size = grid.cell_size();
+    let iso_level = grid.iso_level();
+    let at = |x: usize, y: usize| density[y * width + x];
+    // central differences, one-sided at the border
+    let slope = |low: f32, high: f32, steps: usize| {
+        if steps == 0 {
+            0.0
+        } else {
+            (high - low) / (steps as f32 * cell_size)
+        }
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            let value = at(x, y) - threshold;
+            if value < 0.0 {
+                grid.set(x, y, 0.0);
+                continue;
+            }
+            let (left, right) = (x.saturating_sub(1), (x + 1).min(width - 1));
+            let (bottom, top) = (y.saturating_sub(1), (y + 1).min(height - 1));
+            let gx = slope(at(left, y), at(right, y), right - left);
+            let gy = slope(at(x, bottom), at(x, top), top - bottom);
+            let gradient = (gx * gx + gy * gy).sqrt();
+            let weight = if gradient < f32::EPSILON {
+                1.0
+            } else {
+                (value / gradient / cell_size).min(1.0)
+            };
+            grid.set(x, y, weight.max(iso_level));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::WeightGrid;
+    use crate::generation::noise::Fbm;
+    use crate::generation::landscape::{NoiseTerrain, IslandFalloff, FalloffShape, DomainWarp, write_density};
+
+    fn settings(seed: u64) -> NoiseTerrain {
+        NoiseTerrain {
+            seed,
+            fbm: Fbm { frequency: 0.05, ..Fbm::default() },
+            warp: Some(DomainWarp { amplitude: 4.0, fbm: Fbm { octaves: 2, frequency: 0.03, ..Fbm::default() } }),
+            falloff: Some(IslandFalloff { shape: FalloffShape::Circle, start: 0.5, end: 0.9 }),
+            threshold: -0.1
+        }
+    }
+
+    #[test]
+    pub fn test_same_seed_same_map() {
+        let mut a = WeightGrid::new(65, 65, 2.0, 0.001);
+        let mut b = WeightGrid::new(65, 65, 2.0, 0.001);
+        settings(12).generate(&mut a);
+        settings(12).generate(&mut b);
+        assert_eq!(a.weights(), b.weights());
+        settings(13).generate(&mut b);
+        assert_ne!(a.weights(), b.weights());
+
+        // the island falloff leaves the border and the corners empty
+        let solid = a.weights().iter().filter(|it| **it >= 0.001).count();
+        assert!(solid > 0);
+        for id in 0..65 {
+            assert!(!a.is_solid(id, 0) && !a.is_solid(0, id) && !a.is_solid(id, 64) && !a.is_solid(64, id));
+        }
+
+        let density = settings(12).density(&a);
+        for (weight, density) in a.weights().iter().zip(density.iter()) {
+            assert_eq!(*weight >= 0.001, *density >= -0.1);
+        }
+        let mut higher = NoiseTerrain { threshold: 0.1, ..settings(12) };
+        higher.falloff = None;
+        let mut c = WeightGrid::new(65, 65, 2.0, 0.001);
+        higher.generate(&mut c);
+        let lower = NoiseTerrain { threshold: -0.2, ..higher };
+        let mut d = WeightGrid::new(65, 65, 2.0, 0.001);
+        lower.generate(&mut d);
+        let count = |grid: &WeightGrid| grid.weights().iter().filter(|it| **it >= 0.001).count();
+        assert!(count(&c) < count(&d));
+    }
+
+    #[test]
+    pub fn test_density_crossing() {
+        // a linear ramp crosses the threshold halfway between vertices 2 and 3
+        let mut grid = WeightGrid::new(6, 1, 2.0, 0.001);
+        let density = [0.5, 0.4, 0.3, 0.2, 0.1, 0.0];
+        write_density(&mut grid, &density, 0.25);
+        assert!(grid.is_solid(2, 0) && !grid.is_solid(3, 0));
+        assert!((grid.get(2, 0) - 0.5).abs() < 0.0001);
+        assert!((grid.get(0, 0) - 1.0).abs() < 0.0001);
+    }
+}
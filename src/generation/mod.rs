@@ -0,0 +1,4 @@
+pub mod random;
+pub mod noise;
+pub mod landscape;
+pub mod caves;
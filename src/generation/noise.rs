@@ -0,0 +1,130 @@
+use crate::generation::random::Random;
+
+/// Seeded 2D Perlin gradient noise
+#[derive(Clone)]
+pub struct PerlinNoise {
+    /// Shuffled `0..256` repeated twice, so lookups don't need wrapping
+    permutation: Vec<u8>
+}
+
+const GRADIENTS: [[f32; 2]; 8] = [
+    [1.0, 0.0], [-1.0, 0.0], [0.0, 1.0], [0.0, -1.0],
+    [std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2],
+    [-std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2],
+    [std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2],
+    [-std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2]
+];
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+impl PerlinNoise {
+    pub fn new(seed: u64) -> Self {
+        let mut values = (0..=255u8).collect::<Vec<_>>();
+        Random::new(seed).shuffle(&mut values);
+        let permutation = values.iter().chain(values.iter()).copied().collect();
+        Self { permutation }
+    }
+
+    fn gradient_dot(&self, cell_x: usize, cell_y: usize, dx: f32, dy: f32) -> f32 {
+        let hash = self.permutation[self.permutation[cell_x] as usize + cell_y];
+        let gradient = GRADIENTS[(hash & 7) as usize];
+        gradient[0] * dx + gradient[1] * dy
+    }
+
+    /// Noise value roughly in `[-1, 1]`, zero at integer coordinates
+    pub fn sample(&self, x: f32, y: f32) -> f32 {
+        let (floor_x, floor_y) = (x.floor(), y.floor());
+        let (cell_x, cell_y) = ((floor_x as i64 & 255) as usize, (floor_y as i64 & 255) as usize);
+        let (dx, dy) = (x - floor_x, y - floor_y);
+        let (u, v) = (fade(dx), fade(dy));
+
+        let bottom = lerp(
+            self.gradient_dot(cell_x, cell_y, dx, dy),
+            self.gradient_dot(cell_x + 1, cell_y, dx - 1.0, dy),
+            u
+        );
+        let top = lerp(
+            self.gradient_dot(cell_x, cell_y + 1, dx, dy - 1.0),
+            self.gradient_dot(cell_x + 1, cell_y + 1, dx - 1.0, dy - 1.0),
+            u
+        );
+        // unit gradients reach at most sqrt(1/2) in 2D
+        lerp(bottom, top, v) * std::f32::consts::SQRT_2
+    }
+}
+
+/// Fractal Brownian motion: octaves of noise with rising frequency and falling amplitude
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Fbm {
+    pub octaves: usize,
+    /// Frequency of the first octave in cycles per world unit
+    pub frequency: f32,
+    /// Frequency multiplier between octaves
+    pub lacunarity: f32,
+    /// Amplitude multiplier between octaves
+    pub gain: f32
+}
+
+impl Default for Fbm {
+    fn default() -> Self {
+        Self {
+            octaves: 5,
+            frequency: 1.0 / 16.0,
+            lacunarity: 2.0,
+            gain: 0.5
+        }
+    }
+}
+
+impl Fbm {
+    /// Sum of the octaves normalized back to roughly `[-1, 1]`
+    pub fn sample(&self, noise: &PerlinNoise, point: [f32; 2]) -> f32 {
+        let (mut sum, mut total_amplitude) = (0.0, 0.0);
+        let (mut frequency, mut amplitude) = (self.frequency, 1.0);
+        for octave in 0..self.octaves {
+            // octaves are shifted apart so their lattice zeros don't line up
+            let shift = octave as f32 * 17.31;
+            sum += amplitude * noise.sample(point[0] * frequency + shift, point[1] * frequency - shift);
+            total_amplitude += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        if total_amplitude > 0.0 {
+            sum / total_amplitude
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generation::noise::{PerlinNoise, Fbm};
+
+    #[test]
+    pub fn test_perlin_noise() {
+        let noise = PerlinNoise::new(1);
+        assert_eq!(0.0, noise.sample(3.0, -5.0));
+        let samples = (0..400)
+            .map(|it| noise.sample(it as f32 * 0.37, it as f32 * 0.11 - 20.0))
+            .collect::<Vec<_>>();
+        assert!(samples.iter().all(|it| (-1.0..=1.0).contains(it)));
+        assert!(samples.iter().any(|it| *it > 0.2) && samples.iter().any(|it| *it < -0.2));
+
+        let same = PerlinNoise::new(1);
+        let other = PerlinNoise::new(2);
+        assert_eq!(noise.sample(1.3, 2.7), same.sample(1.3, 2.7));
+        assert_ne!(noise.sample(1.3, 2.7), other.sample(1.3, 2.7));
+
+        let fbm = Fbm { frequency: 0.1, ..Fbm::default() };
+        let value = fbm.sample(&noise, [12.5, 7.25]);
+        assert!((-1.0..=1.0).contains(&value));
+        assert_eq!(value, fbm.sample(&same, [12.5, 7.25]));
+    }
+}
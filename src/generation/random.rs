@@ -0,0 +1,64 @@
+/// Small seeded generator (SplitMix64), so generated maps don't depend on the global macroquad state
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform in `[low, high)`
+    pub fn range(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * self.next_f32()
+    }
+
+    /// Uniform in `0..count`, `count` must not be zero
+    pub fn below(&mut self, count: usize) -> usize {
+        (self.next_u64() % count as u64) as usize
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// Fisher-Yates shuffle
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for id in (1..items.len()).rev() {
+            items.swap(id, self.below(id + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generation::random::Random;
+
+    #[test]
+    pub fn test_same_seed_same_sequence() {
+        let mut a = Random::new(42);
+        let mut b = Random::new(42);
+        let mut c = Random::new(43);
+        let sequence = (0..16).map(|_| a.next_u64()).collect::<Vec<_>>();
+        assert_eq!(sequence, (0..16).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(sequence, (0..16).map(|_| c.next_u64()).collect::<Vec<_>>());
+
+        let mut random = Random::new(7);
+        assert!((0..1000).map(|_| random.next_f32()).all(|it| (0.0..1.0).contains(&it)));
+        assert!((0..1000).map(|_| random.below(5)).all(|it| it < 5));
+    }
+}
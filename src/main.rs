@@ -1,27 +1,153 @@
 pub mod poly_line_2d;
+pub mod terrain;
+pub mod editor;
+pub mod navigation;
+pub mod generation;
+pub mod physics;
 
 use macroquad::prelude::*;
 use crate::poly_line_2d::Painter;
-use crate::poly_line_2d::style::{JointStyle, EndCapStyle, LineStripStyle};
+use crate::poly_line_2d::style::{JointStyle, EndCapStyle, LineStripStyle, ThicknessSpace, DashStyle};
+use crate::terrain::WeightGrid;
+use crate::editor::history::EditHistory;
+use crate::editor::brush::{Brush, BrushSettings, CircleBrush, SquareBrush, StampBrush, Falloff};
+use crate::editor::operation::{BrushOperation, StrokeState, apply_operation};
+use crate::editor::camera::EditorCamera;
+use crate::editor::grid_overlay::{GridOverlay, GridLineStyle};
+use crate::editor::info_panel::InfoPanel;
+use crate::terrain::contour::ContourPipeline;
+use crate::terrain::contour_cache::ContourCache;
+use crate::terrain::shape::Shape;
+use crate::navigation::navmesh::NavMesh;
+use crate::navigation::grid_path::{find_grid_path, nearest_vertex, GridCost, GridPath};
+use crate::terrain::Connectivity;
+use crate::terrain::measure::TerrainStats;
+use crate::terrain::islands::Anchors;
+use crate::physics::debris::{DebrisWorld, DebrisSettings};
+use crate::physics::water::{WaterLayer, WaterSettings};
+use crate::terrain::contour::trace_contours;
+use crate::generation::noise::Fbm;
+use crate::generation::landscape::{NoiseTerrain, DomainWarp, IslandFalloff, FalloffShape};
+use crate::generation::caves::CaveSettings;
+use crate::terrain::smoothing::Smoothing;
+use crate::terrain::simplify::Simplification;
 
 const TILE_SIZE: f32 = 96.0;
 const TERRAIN_THRESHOLD: f32 = 0.001;
+const CHUNK_SIZE: usize = 16;
+const CONTOUR_TOLERANCE_PIXELS: f32 = 0.5;
+const HISTORY_MEMORY_BUDGET: usize = 16 * 1024 * 1024;
+const MIN_BRUSH_RADIUS: f32 = TILE_SIZE / 8.0;
+const MAX_BRUSH_RADIUS: f32 = TILE_SIZE * 16.0;
+const MIN_BRUSH_STRENGTH: f32 = 0.005;
+const MAX_BRUSH_STRENGTH: f32 = 1.0;
+// resolved at compile time, so the stamp loads whatever the working directory
+const STAMP_BRUSH_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/stamp.png");
+const VERTEX_MARKER_HALF_SIZE: f32 = 4.0;
+const MIN_CAMERA_SCALE: f32 = 0.02;
+const MAX_CAMERA_SCALE: f32 = 4.0;
+const CAMERA_ZOOM_STEP: f32 = 0.1;
+const CAMERA_KEYBOARD_PAN_SPEED: f32 = 800.0;
+const CAMERA_FIT_MARGIN: f32 = 32.0;
+const NAV_AGENT_RADIUS: f32 = TILE_SIZE / 4.0;
+const NAV_TOLERANCE: f32 = TILE_SIZE / 8.0;
+const DEBRIS_SUBSTEPS: usize = 4;
+const WATER_ISO_LEVEL: f32 = 0.05;
+const WATER_POUR_AMOUNT: f32 = 0.5;
+const WATER_STEP_TIME: f32 = 1.0 / 60.0;
+const MAX_SIMULATION_FRAME_TIME: f32 = 1.0 / 30.0;
 
 #[macroquad::main("marching_squares_proto")]
 async fn main() {
     let mut painter = Painter::new();
+    painter.set_thickness_space(ThicknessSpace::Screen);
 
-    let mut camera_x = -TILE_SIZE / 4.0;
-    let mut camera_y = -TILE_SIZE / 4.0;
+    let mut camera = EditorCamera::new([-TILE_SIZE / 4.0, -TILE_SIZE / 4.0], 1.0);
+    camera.min_scale = MIN_CAMERA_SCALE;
+    camera.max_scale = MAX_CAMERA_SCALE;
 
-    let mut camera_scale = 1.0;
+    let mut brush: Box<dyn Brush> = Box::new(CircleBrush::new(BrushSettings {
+        radius: TILE_SIZE / 3.0,
+        hardness: 0.75,
+        strength: 0.05,
+        spacing: 0.25,
+        falloff: Falloff::Linear
+    }));
 
-    let inner_radius = TILE_SIZE / 4.0;
-    let outer_radius = TILE_SIZE / 3.0;
+    let mut brush_operation = BrushOperation::Add;
+    let mut stroke: Option<StrokeState> = None;
 
-    let mut screen_drag_state = None;
+    let mut last_drag_position: Option<(f32, f32)> = None;
 
-    let mut weights = [[0.0f32; 129]; 129];
+    let mut grid = WeightGrid::new(129, 129, TILE_SIZE, TERRAIN_THRESHOLD);
+    let mut contour_pipeline = ContourPipeline::default();
+    let mut contour_cache = ContourCache::new(&grid, CHUNK_SIZE, contour_pipeline);
+
+    let mut navmesh: Option<NavMesh> = None;
+    let mut navmesh_outdated = false;
+    let mut show_navmesh = false;
+    let mut path_start = [TILE_SIZE, TILE_SIZE];
+    let mut show_grid_path = false;
+    let mut grid_path: Option<GridPath> = None;
+    // start, goal and connectivity the cached grid path was searched with
+    let mut grid_path_query = None;
+    let mut grid_path_outdated = false;
+    let mut noise_terrain = NoiseTerrain {
+        seed: 1,
+        fbm: Fbm { frequency: 1.0 / (TILE_SIZE * 24.0), ..Fbm::default() },
+        warp: Some(DomainWarp {
+            amplitude: TILE_SIZE * 6.0,
+            fbm: Fbm { octaves: 2, frequency: 1.0 / (TILE_SIZE * 32.0), ..Fbm::default() }
+        }),
+        falloff: Some(IslandFalloff { shape: FalloffShape::Circle, start: 0.55, end: 0.95 }),
+        threshold: 0.0
+    };
+    let mut cave_settings = CaveSettings::default();
+
+    // with debris enabled, solid cut off from the map border falls after every brush stroke or carve
+    let mut debris = DebrisWorld::new(DebrisSettings {
+        gravity: [0.0, TILE_SIZE * 20.0],
+        kill_distance: TILE_SIZE * 8.0,
+        ..DebrisSettings::default()
+    });
+    let mut debris_enabled = false;
+    let debris_anchors = Anchors { border: true, vertices: Vec::new() };
+
+    let mut water = WaterLayer::new(&grid, WATER_ISO_LEVEL, WaterSettings::default());
+    let mut water_time = 0.0;
+
+    let mut terrain_stats: Option<TerrainStats> = None;
+    let mut show_info_panel = true;
+    // the last failure to load the stamp brush, shown in the info panel until a load succeeds
+    let mut stamp_error: Option<String> = None;
+    let info_panel = InfoPanel {
+        position: [8.0, 8.0],
+        font_size: 20.0,
+        padding: 8.0,
+        text_color: Color::new(0.85, 0.9, 0.95, 1.0),
+        background: Color::new(0.0, 0.0, 0.0, 0.6)
+    };
+    let mut grid_path_connectivity = Connectivity::Eight;
+    let mut history = EditHistory::new(HISTORY_MEMORY_BUDGET);
+    let grid_overlay = GridOverlay {
+        min: [0.0, 0.0],
+        max: grid.vertex_position(grid.width() - 1, grid.height() - 1),
+        spacing: TILE_SIZE,
+        major_every: 8,
+        min_pixel_spacing: 12.0,
+        minor: GridLineStyle {
+            color: Color::new(0.175, 0.325, 0.4, 0.5),
+            thickness: 1.0,
+            dash: DashStyle::Dashed { dash: 6.0, gap: 4.0, offset: 0.0 }
+        },
+        major: GridLineStyle {
+            color: Color::new(0.125, 0.575, 0.8, 0.5),
+            thickness: 2.0,
+            dash: DashStyle::Solid
+        },
+        label_size: 16.0,
+        label_color: Color::new(0.4, 0.7, 0.9, 0.8)
+    };
 
     loop {
         clear_background(Color::new(0.03, 0.02, 0.05, 1.0));
@@ -29,75 +155,268 @@ async fn main() {
         let (mouse_x, mouse_y) = mouse_position();
         let (_, mouse_wheel_y) = mouse_wheel();
 
-        camera_scale = (camera_scale + mouse_wheel_y * get_frame_time()).clamp(0.1, 2.0);
+        let ctrl_down = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        let shift_down = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
 
-        let local_tile_size = TILE_SIZE * camera_scale;
+        if ctrl_down && mouse_wheel_y != 0.0 {
+            let settings = brush.settings_mut();
+            settings.radius = (settings.radius * (1.0 + mouse_wheel_y.signum() * 0.1)).clamp(MIN_BRUSH_RADIUS, MAX_BRUSH_RADIUS);
+        } else if shift_down && mouse_wheel_y != 0.0 {
+            let settings = brush.settings_mut();
+            settings.strength = (settings.strength * (1.0 + mouse_wheel_y.signum() * 0.1)).clamp(MIN_BRUSH_STRENGTH, MAX_BRUSH_STRENGTH);
+        } else if mouse_wheel_y != 0.0 {
+            camera.zoom_by_wheel([mouse_x, mouse_y], mouse_wheel_y, CAMERA_ZOOM_STEP);
+        }
 
-        if is_key_pressed(KeyCode::Space) && screen_drag_state.is_none() {
-            screen_drag_state = Some((mouse_x / camera_scale, mouse_y / camera_scale, camera_x, camera_y))
-        } else if is_key_released(KeyCode::Space) && screen_drag_state.is_some() {
-            screen_drag_state = None;
+        if is_key_pressed(KeyCode::LeftBracket) || is_key_pressed(KeyCode::RightBracket) {
+            let factor = if is_key_pressed(KeyCode::LeftBracket) { 1.0 / 1.2 } else { 1.2 };
+            let settings = brush.settings_mut();
+            settings.radius = (settings.radius * factor).clamp(MIN_BRUSH_RADIUS, MAX_BRUSH_RADIUS);
+        }
+        if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::Equal) {
+            let factor = if is_key_pressed(KeyCode::Minus) { 1.0 / 1.2 } else { 1.2 };
+            let settings = brush.settings_mut();
+            settings.strength = (settings.strength * factor).clamp(MIN_BRUSH_STRENGTH, MAX_BRUSH_STRENGTH);
+        }
+        if is_key_pressed(KeyCode::F) {
+            let settings = brush.settings_mut();
+            settings.falloff = settings.falloff.next();
+        }
+        if is_key_pressed(KeyCode::Key1) {
+            brush = Box::new(CircleBrush::new(*brush.settings()));
+        } else if is_key_pressed(KeyCode::Key2) {
+            brush = Box::new(SquareBrush::new(*brush.settings()));
+        } else if is_key_pressed(KeyCode::Key3) {
+            match StampBrush::from_file(STAMP_BRUSH_PATH, *brush.settings()) {
+                Ok(stamp) => {
+                    brush = Box::new(stamp);
+                    stamp_error = None;
+                }
+                Err(error) => {
+                    let message = format!("stamp brush {}: {}", STAMP_BRUSH_PATH, error);
+                    // pressing the key again shouldn't repeat the same report
+                    if stamp_error.as_ref() != Some(&message) {
+                        eprintln!("{}", message);
+                    }
+                    stamp_error = Some(message);
+                }
+            }
         }
 
-        match screen_drag_state {
-            None => {}
-            Some((drag_start_mx, drag_start_my, drag_start_cam_x, drag_start_cam_y)) => {
-                camera_x = drag_start_cam_x - mouse_x / camera_scale + drag_start_mx;
-                camera_y = drag_start_cam_y - mouse_y / camera_scale + drag_start_my;
+        if is_key_pressed(KeyCode::Tab) {
+            brush_operation = brush_operation.next();
+        }
+        if let BrushOperation::Set(value) = brush_operation {
+            if is_key_pressed(KeyCode::Comma) || is_key_pressed(KeyCode::Period) {
+                let step = if is_key_pressed(KeyCode::Comma) { -0.1 } else { 0.1 };
+                brush_operation = BrushOperation::Set((value + step).clamp(0.0, 1.0));
             }
         }
 
-        for j in 0..=128 {
-            let coord_y = (j as f32 * TILE_SIZE - camera_y) * camera_scale;
+        let active_operation = if is_mouse_button_down(MouseButton::Left) {
+            Some(brush_operation)
+        } else if is_mouse_button_down(MouseButton::Right) {
+            Some(BrushOperation::Subtract)
+        } else {
+            None
+        };
 
-            if coord_y < -2.0 {
-                continue;
+        let dragging = is_key_down(KeyCode::Space) || is_mouse_button_down(MouseButton::Middle);
+        if dragging {
+            if let Some((last_x, last_y)) = last_drag_position {
+                camera.pan_by_screen([mouse_x - last_x, mouse_y - last_y]);
             }
+            last_drag_position = Some((mouse_x, mouse_y));
+        } else {
+            last_drag_position = None;
+        }
 
-            if coord_y > screen_height() {
-                break;
-            }
+        let pan_step = CAMERA_KEYBOARD_PAN_SPEED * get_frame_time();
+        let mut keyboard_pan = [0.0, 0.0];
+        if is_key_down(KeyCode::Left) {
+            keyboard_pan[0] += pan_step;
+        }
+        if is_key_down(KeyCode::Right) {
+            keyboard_pan[0] -= pan_step;
+        }
+        if is_key_down(KeyCode::Up) {
+            keyboard_pan[1] += pan_step;
+        }
+        if is_key_down(KeyCode::Down) {
+            keyboard_pan[1] -= pan_step;
+        }
+        camera.pan_by_screen(keyboard_pan);
 
-            for i in 0..=128 {
-                let coord_x = (i as f32 * TILE_SIZE - camera_x) * camera_scale;
+        if is_key_pressed(KeyCode::Home) {
+            let last_vertex = grid.vertex_position(grid.width() - 1, grid.height() - 1);
+            camera.fit([0.0, 0.0], last_vertex, [screen_width(), screen_height()], CAMERA_FIT_MARGIN);
+        }
 
-                if coord_x < -2.0 {
-                    continue;
-                }
+        camera.update(get_frame_time());
+        let camera_scale = camera.scale();
+
+        // vertex markers stick out of their cells by a few pixels, so widen the view accordingly
+        let marker_margin = VERTEX_MARKER_HALF_SIZE / camera_scale;
+        let (visible_min, visible_max) = camera.visible_rect([screen_width(), screen_height()]);
+        let visible_cells = grid.cells_overlapping(
+            [visible_min[0] - marker_margin, visible_min[1] - marker_margin],
+            [visible_max[0] + marker_margin, visible_max[1] + marker_margin]
+        );
 
-                if coord_x > screen_width() {
-                    break;
+        let painting = active_operation.is_some();
+        if painting && !history.is_recording() {
+            history.begin_edit(&grid);
+        } else if !painting && history.is_recording() {
+            if debris_enabled {
+                let islands = grid.detach_floating_islands(&debris_anchors);
+                for island in islands.iter() {
+                    contour_cache.mark_dirty(island.dirty);
                 }
+                debris.spawn(&islands);
+                terrain_stats = None;
+                navmesh_outdated = true;
+                grid_path_outdated = true;
+            }
+            history.end_edit(&grid);
+        }
+
+        let mouse_world = camera.screen_to_world([mouse_x, mouse_y]);
+        stroke = match (active_operation, stroke) {
+            (None, _) => None,
+            (Some(operation), Some(state)) if state.operation == operation => Some(state),
+            (Some(operation), _) => Some(StrokeState::begin(&grid, operation, mouse_world))
+        };
+
+        if let Some(stroke) = stroke.as_mut() {
+            let settings = *brush.settings();
+            for stamp in stroke.advance(mouse_world, settings.stamp_spacing()) {
+                let (xs, ys) = grid.cells_overlapping(
+                    [stamp[0] - settings.radius, stamp[1] - settings.radius],
+                    [stamp[0] + settings.radius, stamp[1] + settings.radius]
+                ).vertex_ranges();
+                let dirty = grid.cells_around_vertices(xs.clone(), ys.clone());
+                apply_operation(
+                    &mut grid,
+                    xs,
+                    ys,
+                    stroke,
+                    settings.strength,
+                    |vertex| brush.influence([vertex[0] - stamp[0], vertex[1] - stamp[1]]),
+                    || rand::gen_range(-1.0, 1.0)
+                );
+                contour_cache.mark_dirty(dirty);
+                navmesh_outdated = true;
+                grid_path_outdated = true;
+                terrain_stats = None;
+            }
+        }
 
-                if is_mouse_button_down(MouseButton::Left) {
-                    let dist = (
-                        (coord_x - mouse_x) * (coord_x - mouse_x) +
-                            (coord_y - mouse_y) * (coord_y - mouse_y)
-                    ).sqrt();
-                    let power = if dist > outer_radius {
-                        0.0
-                    } else if dist < inner_radius {
-                        1.0
-                    } else {
-                        1.0 - (dist - inner_radius) / (outer_radius - inner_radius)
-                    };
-                    weights[j][i] = (weights[j][i] + 0.5 * get_frame_time() * power).clamp(0.0, 1.0);
-                } else if is_mouse_button_down(MouseButton::Right) {
-                    let dist = (
-                        (coord_x - mouse_x)*(coord_x - mouse_x) +
-                            (coord_y - mouse_y)*(coord_y - mouse_y)
-                    ).sqrt();
-                    let power = if dist > outer_radius {
-                        0.0
-                    } else if dist < inner_radius {
-                        1.0
-                    } else {
-                        1.0 - (dist - inner_radius) / (outer_radius - inner_radius)
-                    };
-                    weights[j][i] = (weights[j][i] - 0.5 * get_frame_time() * power).clamp(0.0, 1.0);
+        if is_key_pressed(KeyCode::X) && !painting {
+            history.begin_edit(&grid);
+            let dirty = grid.carve(&Shape::Circle { center: mouse_world, radius: brush.settings().radius });
+            if debris_enabled {
+                let islands = grid.detach_floating_islands(&debris_anchors);
+                for island in islands.iter() {
+                    contour_cache.mark_dirty(island.dirty);
                 }
+                debris.spawn(&islands);
+            }
+            history.end_edit(&grid);
+            contour_cache.mark_dirty(dirty);
+            navmesh_outdated = true;
+            grid_path_outdated = true;
+            terrain_stats = None;
+        }
 
-                let t = weights[j][i];
+        if is_key_pressed(KeyCode::R) && !painting {
+            history.begin_edit(&grid);
+            if shift_down {
+                cave_settings.seed += 1;
+                cave_settings.generate(&mut grid);
+            } else {
+                noise_terrain.seed += 1;
+                noise_terrain.generate(&mut grid);
+            }
+            history.end_edit(&grid);
+            contour_cache.mark_all_dirty();
+            navmesh_outdated = true;
+            grid_path_outdated = true;
+            terrain_stats = None;
+        }
+
+        if ctrl_down && is_key_pressed(KeyCode::Z) && !painting {
+            let changed = if shift_down {
+                history.redo(&mut grid)
+            } else {
+                history.undo(&mut grid)
+            };
+            if changed {
+                // the restored terrain holds the pieces again
+                debris.bodies.clear();
+                contour_cache.mark_all_dirty();
+                navmesh_outdated = true;
+                grid_path_outdated = true;
+                terrain_stats = None;
+            }
+        }
+
+        if is_key_pressed(KeyCode::S) {
+            contour_pipeline.smoothing = match contour_pipeline.smoothing {
+                Smoothing::None => Smoothing::CatmullRom { tolerance: 0.0 },
+                Smoothing::CatmullRom { .. } => Smoothing::Chaikin { tolerance: 0.0 },
+                Smoothing::Chaikin { .. } => Smoothing::None
+            };
+        }
+
+        if is_key_pressed(KeyCode::D) {
+            contour_pipeline.simplification = match contour_pipeline.simplification {
+                Simplification::None => Simplification::DouglasPeucker { tolerance: 0.0 },
+                Simplification::DouglasPeucker { .. } => Simplification::Visvalingam { tolerance: 0.0 },
+                Simplification::Visvalingam { .. } => Simplification::None
+            };
+        }
+
+        // cellular flow moves water by a fixed amount per step, so it's stepped at a fixed rate
+        water_time += get_frame_time().min(MAX_SIMULATION_FRAME_TIME);
+        while water_time >= WATER_STEP_TIME {
+            if is_key_down(KeyCode::W) {
+                water.pour(&grid, mouse_world, brush.settings().radius, WATER_POUR_AMOUNT);
+            }
+            water.step(&grid);
+            water_time -= WATER_STEP_TIME;
+        }
+
+        // water sits behind the terrain outline
+        painter.push_transform();
+        painter.apply_transform(camera.transform());
+        painter.fill_convex_polygons(
+            Color::new(0.2, 0.45, 0.9, 0.35),
+            (visible_cells.y..visible_cells.y + visible_cells.height)
+                .flat_map(|y| (visible_cells.x..visible_cells.x + visible_cells.width).map(move |x| (x, y)))
+                .flat_map(|(x, y)| water.water.cell_solid_polygons(x, y))
+        );
+        for contour in trace_contours(&water.water, visible_cells) {
+            let line_strip_style = if contour.closed {
+                LineStripStyle::Closed
+            } else {
+                LineStripStyle::Open
+            };
+            painter.draw_lines(
+                JointStyle::Bevel,
+                EndCapStyle::Butt,
+                line_strip_style,
+                Color::new(0.4, 0.65, 1.0, 0.8),
+                1.0,
+                &contour.points
+            );
+        }
+        painter.pop_transform();
+
+        let (visible_xs, visible_ys) = visible_cells.vertex_ranges();
+        for j in visible_ys {
+            for i in visible_xs.clone() {
+                let [coord_x, coord_y] = camera.world_to_screen(grid.vertex_position(i, j));
+                let t = grid.get(i, j);
                 let t_opposite = 1.0 - t;
                 let color = Color::new(
                     0.2 * t_opposite + 0.1 * t,
@@ -106,211 +425,153 @@ async fn main() {
                     1.0
                 );
 
-                draw_rectangle(coord_x - 4.0, coord_y - 4.0, 8.0, 8.0, color);
-
-                if i > 0 && j > 0 {
-                    let (
-                        corner_nw, corner_ne,
-                        corner_sw, corner_se
-                    ) = (
-                        weights[j - 1][i - 1], weights[j - 1][i],
-                        weights[j][i - 1],     weights[j][i]
-                    );
-                    let right = if (corner_ne < TERRAIN_THRESHOLD && corner_se < TERRAIN_THRESHOLD) ||
-                        (corner_ne >= TERRAIN_THRESHOLD && corner_se >= TERRAIN_THRESHOLD) {
-                        None
-                    } else {
-                        if corner_ne >= TERRAIN_THRESHOLD {
-                            Some([coord_x, coord_y - local_tile_size * (1.0 - corner_ne)])
-                        } else {
-                            Some([coord_x, coord_y - local_tile_size * (corner_se)])
-                        }
-                    };
-                    let left = if (corner_nw < TERRAIN_THRESHOLD && corner_sw < TERRAIN_THRESHOLD) ||
-                        (corner_nw >= TERRAIN_THRESHOLD && corner_sw >= TERRAIN_THRESHOLD) {
-                        None
-                    } else {
-                        if corner_nw >= TERRAIN_THRESHOLD {
-                            Some([coord_x - local_tile_size, coord_y - local_tile_size * (1.0 - corner_nw)])
-                        } else {
-                            Some([coord_x - local_tile_size, coord_y - local_tile_size * (corner_sw)])
-                        }
-                    };
-                    let bottom = if (corner_sw < TERRAIN_THRESHOLD && corner_se < TERRAIN_THRESHOLD) ||
-                        (corner_sw >= TERRAIN_THRESHOLD && corner_se >= TERRAIN_THRESHOLD) {
-                        None
-                    } else {
-                        if corner_sw >= TERRAIN_THRESHOLD {
-                            Some([coord_x - local_tile_size * (1.0 - corner_sw), coord_y])
-                        } else {
-                            Some([coord_x - local_tile_size * (corner_se), coord_y])
-                        }
-                    };
-                    let top = if (corner_nw < TERRAIN_THRESHOLD && corner_ne < TERRAIN_THRESHOLD) ||
-                        (corner_nw >= TERRAIN_THRESHOLD && corner_ne >= TERRAIN_THRESHOLD) {
-                        None
-                    } else {
-                        if corner_nw >= TERRAIN_THRESHOLD {
-                            Some([coord_x - local_tile_size * (1.0 - corner_nw), coord_y - local_tile_size])
-                        } else {
-                            Some([coord_x - local_tile_size * (corner_ne), coord_y - local_tile_size])
-                        }
-                    };
-                    match (left, right, top, bottom) {
-                        (Some(l), Some(r), None, None) => {
-                            painter.draw_lines(
-                                JointStyle::Miter,
-                                EndCapStyle::Butt,
-                                LineStripStyle::Open,
-                                Color::new(0.1, 1.0, 0.7, 1.0),
-                                2.0,
-                                &[l, r]
-                            );
-                        },
-                        (None, None, Some(t), Some(b)) => {
-                            painter.draw_lines(
-                                JointStyle::Miter,
-                                EndCapStyle::Butt,
-                                LineStripStyle::Open,
-                                Color::new(0.1, 1.0, 0.7, 1.0),
-                                2.0,
-                                &[t, b]
-                            );
-                        },
-                        (Some(l), None, Some(t), None) => {
-                            painter.draw_lines(
-                                JointStyle::Miter,
-                                EndCapStyle::Butt,
-                                LineStripStyle::Open,
-                                Color::new(0.1, 1.0, 0.7, 1.0),
-                                2.0,
-                                &[l, t]
-                            );
-                        },
-                        (None, Some(r), Some(t), None) => {
-                            painter.draw_lines(
-                                JointStyle::Miter,
-                                EndCapStyle::Butt,
-                                LineStripStyle::Open,
-                                Color::new(0.1, 1.0, 0.7, 1.0),
-                                2.0,
-                                &[r, t]
-                            );
-                        },
-                        (Some(l), None, None, Some(b)) => {
-                            painter.draw_lines(
-                                JointStyle::Miter,
-                                EndCapStyle::Butt,
-                                LineStripStyle::Open,
-                                Color::new(0.1, 1.0, 0.7, 1.0),
-                                2.0,
-                                &[l, b]
-                            );
-                        },
-                        (None, Some(r), None, Some(b)) => {
-                            painter.draw_lines(
-                                JointStyle::Miter,
-                                EndCapStyle::Butt,
-                                LineStripStyle::Open,
-                                Color::new(0.1, 1.0, 0.7, 1.0),
-                                2.0,
-                                &[r, b]
-                            );
-                        },
-                        (Some(l), Some(r), Some(t), Some(b)) if corner_nw >= TERRAIN_THRESHOLD && corner_se >= TERRAIN_THRESHOLD => {
-                            painter.draw_lines(
-                                JointStyle::Miter,
-                                EndCapStyle::Butt,
-                                LineStripStyle::Open,
-                                Color::new(0.1, 1.0, 0.7, 1.0),
-                                2.0,
-                                &[r, b]
-                            );
-                            painter.draw_lines(
-                                JointStyle::Miter,
-                                EndCapStyle::Butt,
-                                LineStripStyle::Open,
-                                Color::new(0.1, 1.0, 0.7, 1.0),
-                                2.0,
-                                &[l, t]
-                            );
-                        },
-                        (Some(l), Some(r), Some(t), Some(b)) if corner_ne >= TERRAIN_THRESHOLD && corner_sw >= TERRAIN_THRESHOLD => {
-                            painter.draw_lines(
-                                JointStyle::Miter,
-                                EndCapStyle::Butt,
-                                LineStripStyle::Open,
-                                Color::new(0.1, 1.0, 0.7, 1.0),
-                                2.0,
-                                &[r, t]
-                            );
-                            painter.draw_lines(
-                                JointStyle::Miter,
-                                EndCapStyle::Butt,
-                                LineStripStyle::Open,
-                                Color::new(0.1, 1.0, 0.7, 1.0),
-                                2.0,
-                                &[l, b]
-                            );
-                        },
-                        _ => {}
-                    }
-                }
+                draw_rectangle(
+                    coord_x - VERTEX_MARKER_HALF_SIZE,
+                    coord_y - VERTEX_MARKER_HALF_SIZE,
+                    VERTEX_MARKER_HALF_SIZE * 2.0,
+                    VERTEX_MARKER_HALF_SIZE * 2.0,
+                    color
+                );
             }
         }
 
-        for i in (0..=128).step_by(8) {
-            let color = if i % 16 != 0 {
-                Color::new(
-                    0.2 * 0.75 + 0.1 * 0.25,
-                    0.2 * 0.75 + 0.7 * 0.25,
-                    0.2 * 0.75 + 1.0 * 0.25,
-                    0.5
-                )
+        painter.push_transform();
+        painter.apply_transform(camera.transform());
+
+        // changing the tolerance retraces the whole map, so it only follows the zoom in power-of-two steps;
+        // rounding the scale up keeps the error within the pixel tolerance
+        let contour_tolerance = CONTOUR_TOLERANCE_PIXELS / camera_scale.log2().ceil().exp2();
+        contour_pipeline.smoothing = contour_pipeline.smoothing.with_tolerance(contour_tolerance);
+        contour_pipeline.simplification = contour_pipeline.simplification.with_tolerance(contour_tolerance);
+
+        // whole chunks are traced so contours don't change while the view pans
+        contour_cache.set_pipeline(contour_pipeline);
+        contour_cache.update(&grid, visible_cells);
+        for contour in contour_cache.contours(visible_cells) {
+            let line_strip_style = if contour.closed {
+                LineStripStyle::Closed
             } else {
-                Color::new(
-                    0.2 * 0.25 + 0.1 * 0.75,
-                    0.2 * 0.25 + 0.7 * 0.75,
-                    0.2 * 0.25 + 1.0 * 0.75,
-                    0.5
-                )
+                LineStripStyle::Open
             };
-            {
-                let coord_x = -camera_x * camera_scale - 1.0;
-                let coord_y = (i as f32 * TILE_SIZE - camera_y) * camera_scale - 1.0;
-                draw_rectangle(
-                    coord_x, coord_y,
-                    128.0 * TILE_SIZE * camera_scale + 2.0,
-                    2.0,
-                    color
+            painter.draw_lines(
+                JointStyle::Miter,
+                EndCapStyle::Butt,
+                line_strip_style,
+                Color::new(0.1, 1.0, 0.7, 1.0),
+                2.0,
+                &contour.points
+            );
+        }
+
+        painter.pop_transform();
+
+        grid_overlay.draw(&mut painter, &camera, [screen_width(), screen_height()]);
+
+        if is_key_pressed(KeyCode::N) {
+            show_navmesh = !show_navmesh;
+        }
+        if is_key_pressed(KeyCode::G) {
+            path_start = mouse_world;
+        }
+        // rebuilding takes a while, so wait until the stroke is over
+        if show_navmesh && (navmesh.is_none() || navmesh_outdated) && !painting {
+            navmesh = Some(NavMesh::build(&grid, NAV_AGENT_RADIUS, NAV_TOLERANCE));
+            navmesh_outdated = false;
+        }
+        if let (true, Some(navmesh)) = (show_navmesh, navmesh.as_ref()) {
+            painter.push_transform();
+            painter.apply_transform(camera.transform());
+            for triangle in navmesh.triangles.iter() {
+                painter.draw_lines(
+                    JointStyle::Bevel,
+                    EndCapStyle::Butt,
+                    LineStripStyle::Closed,
+                    Color::new(0.9, 0.6, 0.2, 0.35),
+                    1.0,
+                    triangle
                 );
             }
-            {
-                let coord_y = -camera_y * camera_scale - 1.0;
-                let coord_x = (i as f32 * TILE_SIZE - camera_x) * camera_scale - 1.0;
-                draw_rectangle(
-                    coord_x, coord_y,
+            if let Some(path) = navmesh.find_path(path_start, mouse_world) {
+                painter.draw_lines(
+                    JointStyle::Miter,
+                    EndCapStyle::Square,
+                    LineStripStyle::Open,
+                    Color::new(1.0, 0.8, 0.2, 1.0),
+                    3.0,
+                    &path
+                );
+            }
+            painter.pop_transform();
+        }
+
+        if is_key_pressed(KeyCode::B) {
+            debris_enabled = !debris_enabled;
+        }
+        let debris_dt = get_frame_time().min(MAX_SIMULATION_FRAME_TIME) / DEBRIS_SUBSTEPS as f32;
+        for _ in 0..DEBRIS_SUBSTEPS {
+            debris.step(&grid, debris_dt);
+        }
+        if !debris.bodies.is_empty() {
+            painter.push_transform();
+            painter.apply_transform(camera.transform());
+            for body in debris.bodies.iter() {
+                painter.draw_lines(
+                    JointStyle::Miter,
+                    EndCapStyle::Butt,
+                    LineStripStyle::Closed,
+                    Color::new(0.8, 0.55, 0.3, 1.0),
                     2.0,
-                    128.0 * TILE_SIZE * camera_scale + 2.0,
-                    color
+                    &body.world_outline()
                 );
             }
+            painter.pop_transform();
         }
 
+        if is_key_pressed(KeyCode::P) {
+            show_grid_path = !show_grid_path;
+        }
+        if is_key_pressed(KeyCode::C) {
+            grid_path_connectivity = match grid_path_connectivity {
+                Connectivity::Four => Connectivity::Eight,
+                Connectivity::Eight => Connectivity::Four
+            };
+        }
+        if show_grid_path {
+            let query = (nearest_vertex(&grid, path_start), nearest_vertex(&grid, mouse_world), grid_path_connectivity);
+            // searching the whole grid every frame is wasteful, the path only changes with the query or the terrain
+            if grid_path_outdated || grid_path_query != Some(query) {
+                grid_path = find_grid_path(&grid, query.0, query.1, query.2, GridCost::Blocked);
+                grid_path_query = Some(query);
+                grid_path_outdated = false;
+            }
+            if let Some(grid_path) = grid_path.as_ref() {
+                painter.push_transform();
+                painter.apply_transform(camera.transform());
+                painter.draw_lines(
+                    JointStyle::Bevel,
+                    EndCapStyle::Round,
+                    LineStripStyle::Open,
+                    Color::new(0.3, 0.9, 0.5, 0.9),
+                    4.0,
+                    &grid_path.points(&grid)
+                );
+                painter.pop_transform();
+            }
+        }
+
+        let inner_radius = brush.settings().inner_radius();
+        let outer_radius = brush.settings().radius;
+        painter.push_transform();
+        painter.apply_transform(camera.transform());
         painter.draw_lines_ex(
             JointStyle::Miter,
             EndCapStyle::Butt,
             LineStripStyle::Closed,
             WHITE,
             1.5,
-            (0..72)
-                .map(|degree| (degree as f32 * 5.0).to_radians())
-                .map(|theta|
-                    [
-                        mouse_x + theta.cos() * inner_radius,
-                        mouse_y + theta.sin() * inner_radius
-                    ]
-                )
+            brush.outline(inner_radius)
+                .into_iter()
+                .map(|it| [mouse_world[0] + it[0], mouse_world[1] + it[1]])
         );
 
         painter.draw_lines_ex(
@@ -319,15 +580,26 @@ async fn main() {
             LineStripStyle::Closed,
             GRAY,
             1.2,
-            (0..72)
-                .map(|degree| (degree as f32 * 5.0).to_radians())
-                .map(|theta|
-                    [
-                        mouse_x + theta.cos() * outer_radius,
-                        mouse_y + theta.sin() * outer_radius
-                    ]
-                )
+            brush.outline(outer_radius)
+                .into_iter()
+                .map(|it| [mouse_world[0] + it[0], mouse_world[1] + it[1]])
         );
+        painter.pop_transform();
+
+        if is_key_pressed(KeyCode::I) {
+            show_info_panel = !show_info_panel;
+        }
+        if show_info_panel {
+            let stats = *terrain_stats.get_or_insert_with(|| TerrainStats::measure(&grid));
+            let mut lines = vec![
+                format!("land area: {:.1} tiles", stats.land_area / (TILE_SIZE * TILE_SIZE)),
+                format!("coastline: {:.1} tiles", stats.coastline_length / TILE_SIZE),
+                format!("landmasses: {}, lakes: {}", stats.landmasses, stats.lakes),
+                format!("loops: {} outer, {} holes", stats.outer_loops, stats.holes)
+            ];
+            lines.extend(stamp_error.clone());
+            info_panel.draw(&lines);
+        }
 
         next_frame().await;
     }
@@ -0,0 +1,175 @@
+use std::collections::BinaryHeap;
+use crate::terrain::{WeightGrid, Connectivity};
+use crate::navigation::OpenNode;
+
+/// How solid vertices affect a grid path
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GridCost {
+    /// Solid vertices can't be entered, diagonal steps can't cut their corners
+    Blocked,
+    /// Nothing is blocked, entering a vertex costs `1 + penalty * weight` per unit of distance
+    Weighted { penalty: f32 }
+}
+
+/// Path over grid vertices
+#[derive(Clone, Debug)]
+pub struct GridPath {
+    pub vertices: Vec<(usize, usize)>,
+    /// Total cost in world units
+    pub cost: f32
+}
+
+impl GridPath {
+    /// World-space positions of the path vertices
+    pub fn points(&self, grid: &WeightGrid) -> Vec<[f32; 2]> {
+        self.vertices.iter().map(|&(x, y)| grid.vertex_position(x, y)).collect()
+    }
+}
+
+/// Grid vertex closest to a world-space point, clamped to the grid
+pub fn nearest_vertex(grid: &WeightGrid, point: [f32; 2]) -> (usize, usize) {
+    let to_vertex = |coord: f32, count: usize| {
+        ((coord / grid.cell_size()).round().max(0.0) as usize).min(count.saturating_sub(1))
+    };
+    (to_vertex(point[0], grid.width()), to_vertex(point[1], grid.height()))
+}
+
+/// A* over the grid vertices from `start` to `goal`
+pub fn find_grid_path(
+    grid: &WeightGrid,
+    start: (usize, usize),
+    goal: (usize, usize),
+    connectivity: Connectivity,
+    cost: GridCost
+) -> Option<GridPath> {
+    let (width, height) = (grid.width(), grid.height());
+    if start.0 >= width || start.1 >= height || goal.0 >= width || goal.1 >= height {
+        return None;
+    }
+    let blocked = |x: usize, y: usize| matches!(cost, GridCost::Blocked) && grid.is_solid(x, y);
+    if blocked(start.0, start.1) || blocked(goal.0, goal.1) {
+        return None;
+    }
+
+    let cell_size = grid.cell_size();
+    // weighted steps never cost less than their length, so the heuristic stays admissible
+    let heuristic = |x: usize, y: usize| {
+        let dx = (x as f32 - goal.0 as f32).abs();
+        let dy = (y as f32 - goal.1 as f32).abs();
+        cell_size * match connectivity {
+            Connectivity::Four => dx + dy,
+            Connectivity::Eight => dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dy)
+        }
+    };
+
+    let mut best_cost = vec![f32::MAX; width * height];
+    let mut came_from = vec![usize::MAX; width * height];
+    let start_id = grid.index(start.0, start.1);
+    let goal_id = grid.index(goal.0, goal.1);
+    best_cost[start_id] = 0.0;
+    let mut open = BinaryHeap::new();
+    open.push(OpenNode { estimate: heuristic(start.0, start.1), id: start_id });
+
+    while let Some(OpenNode { estimate, id }) = open.pop() {
+        let (x, y) = (id % width, id / width);
+        if id == goal_id {
+            let mut vertices = vec![goal];
+            let mut current = goal_id;
+            while current != start_id {
+                current = came_from[current];
+                vertices.push((current % width, current / width));
+            }
+            vertices.reverse();
+            return Some(GridPath { vertices, cost: best_cost[goal_id] });
+        }
+        // stale entry of a vertex reached cheaper later
+        if estimate > best_cost[id] + heuristic(x, y) {
+            continue;
+        }
+        for &(dx, dy) in connectivity.offsets() {
+            let (nx, ny) = (x as isize + dx, y as isize + dy);
+            if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                continue;
+            }
+            let (nx, ny) = (nx as usize, ny as usize);
+            if blocked(nx, ny) {
+                continue;
+            }
+            let diagonal = dx != 0 && dy != 0;
+            if diagonal && (blocked(nx, y) || blocked(x, ny)) {
+                continue;
+            }
+            let length = if diagonal { std::f32::consts::SQRT_2 } else { 1.0 } * cell_size;
+            let step = match cost {
+                GridCost::Blocked => length,
+                GridCost::Weighted { penalty } => length * (1.0 + penalty * grid.get(nx, ny).max(0.0))
+            };
+            let neighbour = grid.index(nx, ny);
+            let new_cost = best_cost[id] + step;
+            if new_cost < best_cost[neighbour] {
+                best_cost[neighbour] = new_cost;
+                came_from[neighbour] = id;
+                open.push(OpenNode { estimate: new_cost + heuristic(nx, ny), id: neighbour });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::{WeightGrid, Connectivity};
+    use crate::navigation::grid_path::{find_grid_path, nearest_vertex, GridCost};
+
+    /// A wall at x = 4 with a gap at y = 8
+    fn wall() -> WeightGrid {
+        let mut grid = WeightGrid::new(9, 10, 2.0, 0.001);
+        for y in 0..8 {
+            grid.set(4, y, 1.0);
+        }
+        grid
+    }
+
+    #[test]
+    pub fn test_path_around_wall() {
+        let grid = wall();
+        let path = find_grid_path(&grid, (0, 0), (8, 0), Connectivity::Four, GridCost::Blocked).unwrap();
+        assert_eq!((0, 0), path.vertices[0]);
+        assert_eq!((8, 0), *path.vertices.last().unwrap());
+        assert!(path.vertices.contains(&(4, 8)));
+        assert!((path.cost - 2.0 * (8.0 + 8.0 + 8.0)).abs() < 0.0001);
+        assert!(path.vertices.windows(2).all(|it| {
+            it[0].0.abs_diff(it[1].0) + it[0].1.abs_diff(it[1].1) == 1
+        }));
+
+        let diagonal = find_grid_path(&grid, (0, 0), (8, 0), Connectivity::Eight, GridCost::Blocked).unwrap();
+        assert!(diagonal.cost < path.cost);
+        // the gap can't be entered diagonally past the end of the wall
+        let through_gap = diagonal.vertices.iter().position(|it| it.0 == 4).unwrap();
+        assert_eq!((4, 8), diagonal.vertices[through_gap]);
+        assert_eq!(8, diagonal.vertices[through_gap - 1].1);
+
+        let mut closed = grid.clone();
+        closed.set(4, 8, 1.0);
+        closed.set(4, 9, 1.0);
+        assert!(find_grid_path(&closed, (0, 0), (8, 0), Connectivity::Eight, GridCost::Blocked).is_none());
+        assert!(find_grid_path(&closed, (0, 0), (4, 0), Connectivity::Eight, GridCost::Blocked).is_none());
+    }
+
+    #[test]
+    pub fn test_weighted_cost() {
+        let mut grid = wall();
+        for y in 0..10 {
+            grid.set(4, y, 0.25);
+        }
+        grid.set(4, 0, 1.0);
+        let path = find_grid_path(&grid, (0, 0), (8, 0), Connectivity::Four, GridCost::Weighted { penalty: 4.0 }).unwrap();
+        // crossing the cheap part of the wall beats the straight line through the heavy vertex
+        assert!(!path.vertices.contains(&(4, 0)));
+        assert!(path.vertices.contains(&(4, 1)));
+        assert!((path.cost - 2.0 * (10.0 + 4.0 * 0.25)).abs() < 0.0001);
+
+        assert_eq!((2, 0), nearest_vertex(&grid, [4.9, -3.0]));
+        assert_eq!((8, 9), nearest_vertex(&grid, [100.0, 100.0]));
+    }
+}
@@ -0,0 +1,32 @@
+pub mod triangulation;
+pub mod navmesh;
+pub mod grid_path;
+
+use std::cmp::Ordering;
+
+/// Entry of the A* open set, shared by the navmesh and grid searches
+struct OpenNode {
+    estimate: f32,
+    id: usize
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimate == other.estimate
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    // reversed, so the binary heap pops the smallest estimate first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.partial_cmp(&self.estimate).unwrap_or(Ordering::Equal)
+    }
+}
@@ -0,0 +1,291 @@
+use std::collections::{BinaryHeap, HashMap};
+use crate::terrain::WeightGrid;
+use crate::terrain::contour::{trace_contours, Contour};
+use crate::terrain::simplify::{simplify_contour, Simplification};
+use crate::terrain::measure::signed_area;
+use crate::navigation::OpenNode;
+use crate::navigation::triangulation::{triangulate, point_in_polygon, PolygonWithHoles};
+
+/// Triangulated free space of the terrain with triangle adjacency
+pub struct NavMesh {
+    pub triangles: Vec<[[f32; 2]; 3]>,
+    /// Triangle across the edge from vertex `k` to vertex `k + 1` of each triangle
+    pub neighbours: Vec<[Option<usize>; 3]>
+}
+
+impl NavMesh {
+    /// Builds the mesh over the empty space of `grid` shrunk by `agent_radius`,
+    /// so an agent of that radius can follow any path without touching the terrain or the map border.
+    /// Free-space outlines are simplified with `tolerance` before triangulation
+    pub fn build(grid: &WeightGrid, agent_radius: f32, tolerance: f32) -> Self {
+        let eroded = erode_free_space(grid, agent_radius);
+        let polygons = free_space_polygons(&eroded, tolerance);
+        Self::from_polygons(&polygons)
+    }
+
+    pub fn from_polygons(polygons: &[PolygonWithHoles]) -> Self {
+        let triangles = polygons.iter().flat_map(triangulate).collect::<Vec<_>>();
+        let key = |point: [f32; 2]| (point[0].to_bits(), point[1].to_bits());
+
+        let mut edges = HashMap::new();
+        for (id, triangle) in triangles.iter().enumerate() {
+            for k in 0..3 {
+                edges.insert((key(triangle[k]), key(triangle[(k + 1) % 3])), id);
+            }
+        }
+        // neighbours walk the shared edge in the opposite direction
+        let neighbours = triangles
+            .iter()
+            .map(|triangle| {
+                let mut neighbours = [None; 3];
+                for (k, neighbour) in neighbours.iter_mut().enumerate() {
+                    *neighbour = edges.get(&(key(triangle[(k + 1) % 3]), key(triangle[k]))).copied();
+                }
+                neighbours
+            })
+            .collect();
+        Self { triangles, neighbours }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    pub fn find_triangle(&self, point: [f32; 2]) -> Option<usize> {
+        self.triangles.iter().position(|triangle| {
+            (0..3).all(|k| {
+                let (a, b) = (triangle[k], triangle[(k + 1) % 3]);
+                (b[0] - a[0]) * (point[1] - a[1]) - (b[1] - a[1]) * (point[0] - a[0]) >= 0.0
+            })
+        })
+    }
+
+    /// Shortest path of waypoints from `start` to `goal`, both have to lie on the mesh
+    pub fn find_path(&self, start: [f32; 2], goal: [f32; 2]) -> Option<Vec<[f32; 2]>> {
+        let start_triangle = self.find_triangle(start)?;
+        let goal_triangle = self.find_triangle(goal)?;
+        let corridor = self.find_corridor(start_triangle, goal_triangle, start, goal)?;
+
+        let mut portals = vec![(start, start)];
+        for pair in corridor.windows(2) {
+            let k = self.neighbours[pair[0]].iter().position(|it| *it == Some(pair[1]))?;
+            let triangle = &self.triangles[pair[0]];
+            // walking through a counter-clockwise triangle, the far end of the shared edge is on the left
+            portals.push((triangle[(k + 1) % 3], triangle[k]));
+        }
+        portals.push((goal, goal));
+        Some(string_pull(&portals))
+    }
+
+    fn centroid(&self, id: usize) -> [f32; 2] {
+        let [a, b, c] = self.triangles[id];
+        [(a[0] + b[0] + c[0]) / 3.0, (a[1] + b[1] + c[1]) / 3.0]
+    }
+
+    /// A* over triangles, with edge midpoints as the points travelled through
+    fn find_corridor(&self, start: usize, goal: usize, start_point: [f32; 2], goal_point: [f32; 2]) -> Option<Vec<usize>> {
+        let mut best_cost = vec![f32::MAX; self.triangles.len()];
+        let mut entry_point = vec![start_point; self.triangles.len()];
+        let mut came_from = vec![usize::MAX; self.triangles.len()];
+        let mut open = BinaryHeap::new();
+        best_cost[start] = 0.0;
+        open.push(OpenNode { estimate: distance(start_point, goal_point), id: start });
+
+        while let Some(OpenNode { id, .. }) = open.pop() {
+            if id == goal {
+                let mut corridor = vec![goal];
+                while *corridor.last().unwrap() != start {
+                    corridor.push(came_from[*corridor.last().unwrap()]);
+                }
+                corridor.reverse();
+                return Some(corridor);
+            }
+            for (k, neighbour) in self.neighbours[id].iter().enumerate() {
+                let Some(neighbour) = *neighbour else {
+                    continue;
+                };
+                let triangle = &self.triangles[id];
+                let (a, b) = (triangle[k], triangle[(k + 1) % 3]);
+                let midpoint = if neighbour == goal {
+                    goal_point
+                } else {
+                    [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0]
+                };
+                let cost = best_cost[id] + distance(entry_point[id], midpoint);
+                if cost < best_cost[neighbour] {
+                    best_cost[neighbour] = cost;
+                    entry_point[neighbour] = midpoint;
+                    came_from[neighbour] = id;
+                    open.push(OpenNode { estimate: cost + distance(midpoint, goal_point), id: neighbour });
+                }
+            }
+        }
+        None
+    }
+
+    /// Centroids of the triangles, handy for debug drawing of the adjacency
+    pub fn centroids(&self) -> Vec<[f32; 2]> {
+        (0..self.triangles.len()).map(|id| self.centroid(id)).collect()
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]) * (a[0] - b[0]) + (a[1] - b[1]) * (a[1] - b[1])).sqrt()
+}
+
+/// Positive when `c` lies on the left of the direction from `a` to `b`
+fn side(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// Simple stupid funnel algorithm over `(left, right)` portals
+fn string_pull(portals: &[([f32; 2], [f32; 2])]) -> Vec<[f32; 2]> {
+    let mut path = vec![portals[0].0];
+    let (mut apex, mut left, mut right) = (portals[0].0, portals[0].0, portals[0].1);
+    let (mut left_id, mut right_id) = (0, 0);
+
+    let mut id = 1;
+    while id < portals.len() {
+        let (new_left, new_right) = portals[id];
+
+        if side(apex, right, new_right) >= 0.0 {
+            if apex == right || side(apex, left, new_right) < 0.0 {
+                right = new_right;
+                right_id = id;
+            } else {
+                // the right side crossed over the left one, the left corner becomes a waypoint
+                apex = left;
+                path.push(apex);
+                right = apex;
+                right_id = left_id;
+                id = left_id + 1;
+                continue;
+            }
+        }
+
+        if side(apex, left, new_left) <= 0.0 {
+            if apex == left || side(apex, right, new_left) > 0.0 {
+                left = new_left;
+                left_id = id;
+            } else {
+                apex = right;
+                path.push(apex);
+                left = apex;
+                left_id = right_id;
+                id = right_id + 1;
+                continue;
+            }
+        }
+        id += 1;
+    }
+
+    let goal = portals[portals.len() - 1].0;
+    if *path.last().unwrap() != goal {
+        path.push(goal);
+    }
+    path
+}
+
+/// Grid whose solid is the original one grown by `radius`, with the map border treated as solid
+pub fn erode_free_space(grid: &WeightGrid, radius: f32) -> WeightGrid {
+    let mut eroded = grid.clone();
+    let cell_size = grid.cell_size();
+    let map_max = grid.vertex_position(grid.width().saturating_sub(1), grid.height().saturating_sub(1));
+    let search_distance = radius + cell_size * 2.0;
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            let point = grid.vertex_position(x, y);
+            let border_distance = point[0].min(point[1]).min(map_max[0] - point[0]).min(map_max[1] - point[1]);
+            let signed_distance = grid.signed_distance(point, search_distance).min(border_distance) - radius;
+            let mut weight = (-signed_distance / cell_size).clamp(0.0, 1.0);
+            if border_distance <= 0.0 {
+                // keeps free space off the border so every free-space contour is a closed loop
+                weight = weight.max(grid.iso_level());
+            }
+            eroded.set(x, y, weight);
+        }
+    }
+    eroded
+}
+
+/// Free-space regions: each hole of the solid becomes an outline, islands inside it become its holes
+pub fn free_space_polygons(grid: &WeightGrid, tolerance: f32) -> Vec<PolygonWithHoles> {
+    let loops = trace_contours(grid, grid.cell_rect())
+        .iter()
+        .filter(|it| it.closed)
+        .map(|it| simplify_contour(it, Simplification::DouglasPeucker { tolerance }))
+        .filter(|it| it.points.len() >= 3)
+        .collect::<Vec<Contour>>();
+
+    // solid holes wind backwards, reversed they are outlines of the free space
+    let mut polygons = loops
+        .iter()
+        .filter(|it| signed_area(&it.points) < 0.0)
+        .map(|it| PolygonWithHoles { outline: it.points.iter().rev().copied().collect(), holes: Vec::new() })
+        .collect::<Vec<_>>();
+
+    for island in loops.iter().filter(|it| signed_area(&it.points) > 0.0) {
+        // the innermost outline containing the island is the one with the smallest area
+        let owner = polygons
+            .iter()
+            .enumerate()
+            .filter(|(_, polygon)| point_in_polygon(island.points[0], &polygon.outline))
+            .min_by(|a, b| signed_area(&a.1.outline).partial_cmp(&signed_area(&b.1.outline)).unwrap())
+            .map(|(id, _)| id);
+        if let Some(owner) = owner {
+            polygons[owner].holes.push(island.points.iter().rev().copied().collect());
+        }
+    }
+    polygons
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::WeightGrid;
+    use crate::navigation::navmesh::NavMesh;
+
+    /// Vertical wall at x = 10 with a gap between y = 14 and y = 17
+    fn wall_with_gap() -> WeightGrid {
+        let mut grid = WeightGrid::new(21, 21, 1.0, 0.001);
+        for y in 0..21 {
+            if !(14..=17).contains(&y) {
+                grid.set(10, y, 1.0);
+                grid.set(11, y, 1.0);
+            }
+        }
+        grid
+    }
+
+    #[test]
+    pub fn test_path_goes_through_gap() {
+        let grid = wall_with_gap();
+        let navmesh = NavMesh::build(&grid, 0.5, 0.01);
+        assert!(!navmesh.is_empty());
+        assert!(navmesh.find_triangle([10.5, 3.0]).is_none());
+        assert!(navmesh.find_triangle([0.2, 3.0]).is_none());
+
+        let path = navmesh.find_path([4.0, 3.0], [17.0, 3.0]).unwrap();
+        assert_eq!([4.0, 3.0], path[0]);
+        assert_eq!([17.0, 3.0], *path.last().unwrap());
+        assert!(path.len() >= 4);
+        assert!(path.iter().any(|it| it[1] > 13.0 && it[0] > 9.0 && it[0] < 12.5));
+        for pair in path.windows(2) {
+            assert!(grid.line_of_sight(pair[0], pair[1]));
+        }
+
+        let direct = navmesh.find_path([4.0, 3.0], [4.0, 18.0]).unwrap();
+        assert_eq!(vec![[4.0, 3.0], [4.0, 18.0]], direct);
+    }
+
+    #[test]
+    pub fn test_disconnected_regions() {
+        let mut grid = WeightGrid::new(21, 21, 1.0, 0.001);
+        for y in 0..21 {
+            grid.set(10, y, 1.0);
+        }
+        let navmesh = NavMesh::build(&grid, 0.5, 0.01);
+        assert!(navmesh.find_triangle([4.0, 3.0]).is_some());
+        assert!(navmesh.find_triangle([17.0, 3.0]).is_some());
+        assert!(navmesh.find_path([4.0, 3.0], [17.0, 3.0]).is_none());
+    }
+}
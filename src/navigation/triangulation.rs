@@ -0,0 +1,209 @@
+/// Polygon with holes. The outline has a positive signed area, holes have negative ones
+#[derive(Clone, Debug)]
+pub struct PolygonWithHoles {
+    pub outline: Vec<[f32; 2]>,
+    pub holes: Vec<Vec<[f32; 2]>>
+}
+
+fn cross(o: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+/// Point in polygon by crossing parity
+pub fn point_in_polygon(point: [f32; 2], polygon: &[[f32; 2]]) -> bool {
+    let mut inside = false;
+    for (id, a) in polygon.iter().enumerate() {
+        let b = polygon[(id + 1) % polygon.len()];
+        if (a[1] > point[1]) != (b[1] > point[1]) {
+            let x = a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]);
+            if point[0] < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+fn point_in_triangle(point: [f32; 2], a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> bool {
+    cross(a, b, point) >= 0.0 && cross(b, c, point) >= 0.0 && cross(c, a, point) >= 0.0
+}
+
+/// Joins the holes into the outline with zero-width bridges so the result is a single simple loop
+fn bridge_holes(polygon: &PolygonWithHoles) -> Vec<[f32; 2]> {
+    let mut outline = polygon.outline.clone();
+    let mut holes = polygon.holes.iter().filter(|it| it.len() >= 3).collect::<Vec<_>>();
+    // the rightmost hole first, so bridges never cross holes merged later
+    let rightmost = |hole: &Vec<[f32; 2]>| hole.iter().map(|it| it[0]).fold(f32::MIN, f32::max);
+    holes.sort_by(|a, b| rightmost(b).partial_cmp(&rightmost(a)).unwrap());
+
+    for hole in holes {
+        let (hole_id, &hole_point) = hole
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1[0].partial_cmp(&b.1[0]).unwrap())
+            .unwrap();
+        let Some(outline_id) = find_bridge(&outline, hole_point) else {
+            continue;
+        };
+        let mut merged = Vec::with_capacity(outline.len() + hole.len() + 2);
+        merged.extend_from_slice(&outline[..=outline_id]);
+        merged.extend(hole[hole_id..].iter().chain(hole[..=hole_id].iter()));
+        merged.extend_from_slice(&outline[outline_id..]);
+        outline = merged;
+    }
+    outline
+}
+
+/// Outline vertex visible from `point` which lies inside of the outline, found by casting a ray to the right
+fn find_bridge(outline: &[[f32; 2]], point: [f32; 2]) -> Option<usize> {
+    let mut closest_x = f32::MAX;
+    let mut candidate = None;
+    for (id, a) in outline.iter().enumerate() {
+        let next = (id + 1) % outline.len();
+        let b = outline[next];
+        if (a[1] > point[1]) == (b[1] > point[1]) && a[1] != point[1] {
+            continue;
+        }
+        let x = if (b[1] - a[1]).abs() < f32::EPSILON {
+            a[0].max(b[0])
+        } else {
+            a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0])
+        };
+        if x >= point[0] && x < closest_x {
+            closest_x = x;
+            candidate = Some(if a[0] > b[0] { id } else { next });
+        }
+    }
+    let candidate = candidate?;
+
+    // a reflex vertex inside the triangle point - hit - candidate could block the bridge,
+    // the one closest in angle to the ray is visible then
+    let hit = [closest_x, point[1]];
+    let candidate_point = outline[candidate];
+    let mut best = candidate;
+    let mut best_angle = f32::MAX;
+    for (id, vertex) in outline.iter().enumerate() {
+        if id == candidate || vertex[0] < point[0] {
+            continue;
+        }
+        let inside = point_in_triangle(*vertex, point, hit, candidate_point)
+            || point_in_triangle(*vertex, point, candidate_point, hit);
+        if !inside {
+            continue;
+        }
+        let prev = outline[(id + outline.len() - 1) % outline.len()];
+        let next = outline[(id + 1) % outline.len()];
+        if cross(prev, *vertex, next) > 0.0 {
+            continue;
+        }
+        let angle = (vertex[1] - point[1]).abs().atan2(vertex[0] - point[0]);
+        if angle < best_angle {
+            best_angle = angle;
+            best = id;
+        }
+    }
+    Some(best)
+}
+
+/// Ear clipping triangulation. Returns triangles as triples of points with a positive signed area
+pub fn triangulate(polygon: &PolygonWithHoles) -> Vec<[[f32; 2]; 3]> {
+    let points = bridge_holes(polygon);
+    let mut remaining = (0..points.len()).collect::<Vec<_>>();
+    let mut triangles = Vec::new();
+
+    let mut misses = 0;
+    let mut id = 0;
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let (prev, current, next) = (
+            remaining[(id + count - 1) % count],
+            remaining[id % count],
+            remaining[(id + 1) % count]
+        );
+        let (a, b, c) = (points[prev], points[current], points[next]);
+        let area = cross(a, b, c);
+        let is_ear = area > 0.0 && !remaining.iter().any(|&other| {
+            let p = points[other];
+            other != prev && other != current && other != next
+                && p != a && p != b && p != c
+                && point_in_triangle(p, a, b, c)
+        });
+        if is_ear || area.abs() < f32::EPSILON || misses > count {
+            // degenerate corners from bridges are dropped. A simple loop always has an ear, so being stuck
+            // means the outline crosses itself, and forcing it forward loses the area of the clipped corner
+            debug_assert!(is_ear || area.abs() < f32::EPSILON, "no ear among {} remaining vertices", count);
+            if area > 0.0 {
+                triangles.push([a, b, c]);
+            }
+            remaining.remove(id % count);
+            misses = 0;
+        } else {
+            id += 1;
+            misses += 1;
+        }
+        id %= remaining.len();
+    }
+    if remaining.len() == 3 {
+        let (a, b, c) = (points[remaining[0]], points[remaining[1]], points[remaining[2]]);
+        if cross(a, b, c) > 0.0 {
+            triangles.push([a, b, c]);
+        }
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::navigation::triangulation::{triangulate, PolygonWithHoles};
+    use crate::terrain::measure::signed_area;
+
+    fn total_area(triangles: &[[[f32; 2]; 3]]) -> f32 {
+        triangles.iter().map(|it| signed_area(it)).sum()
+    }
+
+    #[test]
+    pub fn test_concave_polygon() {
+        // an L shape
+        let outline = vec![[0.0, 0.0], [0.0, 4.0], [4.0, 4.0], [4.0, 2.0], [2.0, 2.0], [2.0, 0.0]];
+        let outline = outline.into_iter().rev().collect::<Vec<_>>();
+        assert!(signed_area(&outline) > 0.0);
+        let triangles = triangulate(&PolygonWithHoles { outline: outline.clone(), holes: vec![] });
+        assert_eq!(4, triangles.len());
+        assert!((total_area(&triangles) - signed_area(&outline)).abs() < 0.001);
+        assert!(triangles.iter().all(|it| signed_area(it) > 0.0));
+    }
+
+    #[test]
+    pub fn test_polygon_with_holes() {
+        let outline = vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]];
+        let hole = |x: f32, y: f32| vec![[x, y], [x, y + 2.0], [x + 2.0, y + 2.0], [x + 2.0, y]];
+        let polygon = PolygonWithHoles {
+            outline: outline.clone(),
+            holes: vec![hole(2.0, 2.0), hole(6.0, 5.0)]
+        };
+        assert!(signed_area(&polygon.holes[0]) < 0.0);
+        let triangles = triangulate(&polygon);
+        assert_eq!(4 + 8 + 2 * 2 - 2, triangles.len());
+        assert!((total_area(&triangles) - 92.0).abs() < 0.001);
+        assert!(triangles.iter().all(|it| signed_area(it) > 0.0));
+    }
+
+    #[test]
+    pub fn test_bridges_through_reflex_outline() {
+        // a comb whose teeth hide the bridge targets of a row of holes, some sharing rows with outline vertices
+        let mut outline = vec![[0.0, 0.0], [20.0, 0.0], [20.0, 12.0]];
+        for tooth in (0..4).rev() {
+            let x = tooth as f32 * 5.0;
+            outline.extend_from_slice(&[[x + 4.0, 12.0], [x + 4.0, 8.0], [x + 3.0, 8.0], [x + 3.0, 12.0]]);
+        }
+        outline.push([0.0, 12.0]);
+        let hole = |x: f32, y: f32| vec![[x, y], [x, y + 2.0], [x + 1.0, y + 1.0], [x + 2.0, y + 2.0], [x + 2.0, y]];
+        let holes = vec![hole(1.0, 2.0), hole(6.0, 2.0), hole(11.0, 4.0), hole(16.0, 6.0), hole(8.0, 5.0)];
+        let polygon = PolygonWithHoles { outline: outline.clone(), holes: holes.clone() };
+        let expected = signed_area(&outline) + holes.iter().map(|it| signed_area(it)).sum::<f32>();
+
+        let triangles = triangulate(&polygon);
+        assert!((total_area(&triangles) - expected).abs() < 0.001, "{} != {}", total_area(&triangles), expected);
+        assert!(triangles.iter().all(|it| signed_area(it) > 0.0));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::terrain::WeightGrid;
+use crate::terrain::islands::DetachedIsland;
+use crate::terrain::measure::{signed_area, centroid};
+
+/// Rigid body made of a piece of terrain cut loose from its anchors
+#[derive(Clone)]
+pub struct DebrisBody {
+    /// Local weights of the piece, kept so it can be stamped back into a terrain
+    pub field: WeightGrid,
+    /// Position of the vertex (0, 0) of `field` relative to the centre of mass
+    pub field_offset: [f32; 2],
+    /// Outer loop relative to the centre of mass
+    pub outline: Vec<[f32; 2]>,
+    /// World-space centre of mass
+    pub position: [f32; 2],
+    pub rotation: f32,
+    pub velocity: [f32; 2],
+    pub angular_velocity: f32,
+    /// Mass and moment of inertia for a density of one per square world unit
+    pub mass: f32,
+    pub inertia: f32
+}
+
+fn cross(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+impl DebrisBody {
+    /// Body at rest in the place the island was cut out of. `None` for an island without area
+    pub fn from_island(island: &DetachedIsland) -> Option<Self> {
+        let outline = island.outline()?;
+        let mass = signed_area(&outline);
+        if mass <= f32::EPSILON {
+            return None;
+        }
+        let center = centroid(&outline)?;
+        let outline = outline.iter().map(|it| [it[0] - center[0], it[1] - center[1]]).collect::<Vec<_>>();
+
+        // second moment of the polygon about its centroid
+        let mut inertia = 0.0;
+        for (id, a) in outline.iter().enumerate() {
+            let b = outline[(id + 1) % outline.len()];
+            inertia += cross(*a, b) * (dot(*a, *a) + dot(*a, b) + dot(b, b));
+        }
+        Some(Self {
+            field: island.field.clone(),
+            field_offset: [island.origin[0] - center[0], island.origin[1] - center[1]],
+            outline,
+            position: center,
+            rotation: 0.0,
+            velocity: [0.0, 0.0],
+            angular_velocity: 0.0,
+            mass,
+            inertia: (inertia / 12.0).abs().max(f32::EPSILON)
+        })
+    }
+
+    /// Body-space point to world space
+    pub fn to_world(&self, point: [f32; 2]) -> [f32; 2] {
+        let (sn, cs) = self.rotation.sin_cos();
+        [
+            self.position[0] + point[0] * cs - point[1] * sn,
+            self.position[1] + point[0] * sn + point[1] * cs
+        ]
+    }
+
+    pub fn world_outline(&self) -> Vec<[f32; 2]> {
+        self.outline.iter().map(|it| self.to_world(*it)).collect()
+    }
+
+    /// Velocity of the body at a world-space point
+    fn point_velocity(&self, point: [f32; 2]) -> [f32; 2] {
+        let r = [point[0] - self.position[0], point[1] - self.position[1]];
+        [
+            self.velocity[0] - self.angular_velocity * r[1],
+            self.velocity[1] + self.angular_velocity * r[0]
+        ]
+    }
+
+    fn apply_impulse(&mut self, point: [f32; 2], impulse: [f32; 2]) {
+        let r = [point[0] - self.position[0], point[1] - self.position[1]];
+        self.velocity[0] += impulse[0] / self.mass;
+        self.velocity[1] += impulse[1] / self.mass;
+        self.angular_velocity += cross(r, impulse) / self.inertia;
+    }
+
+    /// Impulse along `direction` that changes the point velocity along it by `delta`
+    fn impulse_scale(&self, point: [f32; 2], direction: [f32; 2], delta: f32) -> f32 {
+        let r = [point[0] - self.position[0], point[1] - self.position[1]];
+        let arm = cross(r, direction);
+        delta / (1.0 / self.mass + arm * arm / self.inertia)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DebrisSettings {
+    /// Acceleration in world units per second squared
+    pub gravity: [f32; 2],
+    /// Share of the normal velocity kept after hitting the terrain
+    pub restitution: f32,
+    pub friction: f32,
+    /// Bodies falling this far below the bottom of the terrain are removed
+    pub kill_distance: f32
+}
+
+impl Default for DebrisSettings {
+    fn default() -> Self {
+        Self {
+            gravity: [0.0, 9.81],
+            restitution: 0.2,
+            friction: 0.6,
+            kill_distance: 100.0
+        }
+    }
+}
+
+/// Falling debris colliding with the outline vertices against the terrain iso-line
+pub struct DebrisWorld {
+    pub bodies: Vec<DebrisBody>,
+    pub settings: DebrisSettings
+}
+
+struct Contact {
+    point: [f32; 2],
+    normal: [f32; 2],
+    depth: f32
+}
+
+impl DebrisWorld {
+    pub fn new(settings: DebrisSettings) -> Self {
+        Self { bodies: Vec::new(), settings }
+    }
+
+    /// Turns the islands into bodies, skipping the ones without area
+    pub fn spawn(&mut self, islands: &[DetachedIsland]) {
+        self.bodies.extend(islands.iter().filter_map(DebrisBody::from_island));
+    }
+
+    pub fn step(&mut self, terrain: &WeightGrid, dt: f32) {
+        let settings = self.settings;
+        let cells = terrain.cell_rect();
+        let grid_max = terrain.vertex_position(cells.width, cells.height);
+        for body in self.bodies.iter_mut() {
+            body.velocity[0] += settings.gravity[0] * dt;
+            body.velocity[1] += settings.gravity[1] * dt;
+            body.position[0] += body.velocity[0] * dt;
+            body.position[1] += body.velocity[1] * dt;
+            body.rotation += body.angular_velocity * dt;
+
+            let contacts = body
+                .world_outline()
+                .into_iter()
+                .filter(|it| it[0] >= 0.0 && it[1] >= 0.0 && it[0] <= grid_max[0] && it[1] <= grid_max[1])
+                .filter_map(|point| {
+                    let surface = terrain.closest_surface(point, terrain.cell_size())?;
+                    (surface.signed_distance < 0.0).then(|| Contact {
+                        point,
+                        normal: surface.normal,
+                        depth: -surface.signed_distance
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            // one impulse at the middle of the contacts, so a flat landing doesn't start a spin
+            if !contacts.is_empty() {
+                let count = contacts.len() as f32;
+                let sum = contacts.iter().fold([0.0, 0.0, 0.0, 0.0], |sum, it| {
+                    [sum[0] + it.point[0], sum[1] + it.point[1], sum[2] + it.normal[0], sum[3] + it.normal[1]]
+                });
+                let point = [sum[0] / count, sum[1] / count];
+                let length = (sum[2] * sum[2] + sum[3] * sum[3]).sqrt().max(f32::EPSILON);
+                let normal = [sum[2] / length, sum[3] / length];
+
+                let velocity = body.point_velocity(point);
+                let normal_speed = dot(velocity, normal);
+                if normal_speed < 0.0 {
+                    let normal_impulse = body.impulse_scale(point, normal, -(1.0 + settings.restitution) * normal_speed);
+                    body.apply_impulse(point, [normal[0] * normal_impulse, normal[1] * normal_impulse]);
+
+                    let velocity = body.point_velocity(point);
+                    let tangent = [-normal[1], normal[0]];
+                    let friction_impulse = body
+                        .impulse_scale(point, tangent, -dot(velocity, tangent))
+                        .clamp(-settings.friction * normal_impulse, settings.friction * normal_impulse);
+                    body.apply_impulse(point, [tangent[0] * friction_impulse, tangent[1] * friction_impulse]);
+                }
+            }
+            // pushing out by the deepest contact only keeps resting bodies from jittering
+            if let Some(deepest) = contacts.iter().max_by(|a, b| a.depth.partial_cmp(&b.depth).unwrap()) {
+                body.position[0] += deepest.normal[0] * deepest.depth;
+                body.position[1] += deepest.normal[1] * deepest.depth;
+            }
+        }
+        self.bodies.retain(|it| it.position[1] < grid_max[1] + settings.kill_distance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::WeightGrid;
+    use crate::terrain::islands::Anchors;
+    use crate::physics::debris::{DebrisWorld, DebrisSettings};
+
+    #[test]
+    pub fn test_detached_island_falls_onto_the_ground() {
+        let mut grid = WeightGrid::new(24, 24, 1.0, 0.001);
+        for x in 0..24 {
+            for y in 20..24 {
+                grid.set(x, y, 0.5);
+            }
+        }
+        for y in 4..=7 {
+            for x in 8..=13 {
+                grid.set(x, y, 0.5);
+            }
+        }
+        let islands = grid.detach_floating_islands(&Anchors { border: true, vertices: vec![] });
+        let mut world = DebrisWorld::new(DebrisSettings { gravity: [0.0, 20.0], ..DebrisSettings::default() });
+        world.spawn(&islands);
+        assert_eq!(1, world.bodies.len());
+        let body = &world.bodies[0];
+        assert!((body.position[0] - 10.5).abs() < 0.001 && (body.position[1] - 5.5).abs() < 0.001);
+        assert!((body.mass - (6.0 * 4.0 - 0.5)).abs() < 0.001);
+        let start = body.world_outline();
+        assert!(start.iter().zip(islands[0].outline().unwrap()).all(|(a, b)| {
+            (a[0] - b[0]).abs() < 0.001 && (a[1] - b[1]).abs() < 0.001
+        }));
+
+        for _ in 0..600 {
+            world.step(&grid, 1.0 / 60.0);
+        }
+        let body = &world.bodies[0];
+        // the ground surface is at y = 19.5, the body is 4 units high
+        assert!((body.position[1] - 17.5).abs() < 0.25, "{:?}", body.position);
+        assert!(body.velocity[1].abs() < 1.0);
+        assert!(body.rotation.abs() < 0.05);
+
+        // without ground the body falls out of the world
+        let empty = WeightGrid::new(24, 24, 1.0, 0.001);
+        for _ in 0..600 {
+            world.step(&empty, 1.0 / 60.0);
+        }
+        assert!(world.bodies.is_empty());
+    }
+}
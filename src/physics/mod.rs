@@ -0,0 +1,2 @@
+pub mod debris;
+pub mod water;
@@ -0,0 +1,194 @@
+use crate::terrain::WeightGrid;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WaterSettings {
+    /// Amount a vertex holds without any pressure from above, one fills the whole cell height
+    pub max_amount: f32,
+    /// Extra amount per vertex of depth a vertex holds under the water above it,
+    /// which is what pushes water up on the other side of a U-bend
+    pub compression: f32,
+    /// Smaller amounts evaporate
+    pub min_amount: f32,
+    /// Share of the computed flow moved in one step, lower values are calmer
+    pub flow_speed: f32
+}
+
+impl Default for WaterSettings {
+    fn default() -> Self {
+        Self {
+            max_amount: 1.0,
+            compression: 0.02,
+            min_amount: 0.001,
+            flow_speed: 1.0
+        }
+    }
+}
+
+/// Water amounts on the vertices of a second grid matching the terrain one.
+/// Water flows down, spreads sideways and rises under pressure through the empty terrain vertices,
+/// and is contoured like the terrain at the iso-level of `water`
+pub struct WaterLayer {
+    pub water: WeightGrid,
+    pub settings: WaterSettings
+}
+
+impl WaterLayer {
+    /// Dry layer over the vertices of `terrain`
+    pub fn new(terrain: &WeightGrid, iso_level: f32, settings: WaterSettings) -> Self {
+        Self {
+            water: WeightGrid::new(terrain.width(), terrain.height(), terrain.cell_size(), iso_level),
+            settings
+        }
+    }
+
+    pub fn total(&self) -> f32 {
+        self.water.weights().iter().sum()
+    }
+
+    /// Adds `amount` to the open vertices within `radius` of `center`
+    pub fn pour(&mut self, terrain: &WeightGrid, center: [f32; 2], radius: f32, amount: f32) {
+        let (xs, ys) = self.water
+            .cells_overlapping([center[0] - radius, center[1] - radius], [center[0] + radius, center[1] + radius])
+            .vertex_ranges();
+        for y in ys {
+            for x in xs.clone() {
+                let position = self.water.vertex_position(x, y);
+                let distance = ((position[0] - center[0]).powi(2) + (position[1] - center[1]).powi(2)).sqrt();
+                if distance <= radius && !terrain.is_solid(x, y) {
+                    self.water.set(x, y, self.water.get(x, y) + amount);
+                }
+            }
+        }
+    }
+
+    /// Amount the lower of two stacked vertices holds when they share `total` at rest
+    fn stable_lower_amount(&self, total: f32) -> f32 {
+        let (max, compression) = (self.settings.max_amount, self.settings.compression);
+        if total <= max {
+            max
+        } else if total < 2.0 * max + compression {
+            (max * max + total * compression) / (max + compression)
+        } else {
+            (total + compression) / 2.0
+        }
+    }
+
+    /// Moves water one step, vertices with a larger `y` are lower. Water inside the solid disappears
+    pub fn step(&mut self, terrain: &WeightGrid) {
+        let (width, height) = (self.water.width(), self.water.height());
+        let settings = self.settings;
+        let current = self.water.weights().to_vec();
+        let mut next = current.clone();
+        let open = |x: usize, y: usize| !terrain.is_solid(x, y);
+
+        for y in 0..height {
+            for x in 0..width {
+                let id = y * width + x;
+                if !open(x, y) {
+                    next[id] = 0.0;
+                    continue;
+                }
+                let mut remaining = current[id];
+                if remaining < settings.min_amount {
+                    continue;
+                }
+                let mut flow_to = |target: usize, flow: f32, remaining: &mut f32| {
+                    let flow = (flow * settings.flow_speed).clamp(0.0, *remaining);
+                    next[id] -= flow;
+                    next[target] += flow;
+                    *remaining -= flow;
+                };
+
+                if y + 1 < height && open(x, y + 1) {
+                    let below = id + width;
+                    let flow = self.stable_lower_amount(remaining + current[below]) - current[below];
+                    flow_to(below, flow, &mut remaining);
+                }
+                if x > 0 && open(x - 1, y) && remaining > 0.0 {
+                    flow_to(id - 1, (remaining - current[id - 1]) / 4.0, &mut remaining);
+                }
+                if x + 1 < width && open(x + 1, y) && remaining > 0.0 {
+                    flow_to(id + 1, (remaining - current[id + 1]) / 3.0, &mut remaining);
+                }
+                if y > 0 && open(x, y - 1) && remaining > 0.0 {
+                    let above = id - width;
+                    let flow = remaining - self.stable_lower_amount(remaining + current[above]);
+                    flow_to(above, flow, &mut remaining);
+                }
+            }
+        }
+        for amount in next.iter_mut() {
+            if *amount < settings.min_amount {
+                *amount = 0.0;
+            }
+        }
+        self.water.weights_mut().copy_from_slice(&next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::WeightGrid;
+    use crate::terrain::contour::trace_contours;
+    use crate::physics::water::{WaterLayer, WaterSettings};
+
+    /// U-shaped basin: floor at y = 10 between walls at x = 3 and x = 12
+    fn basin() -> WeightGrid {
+        let mut terrain = WeightGrid::new(16, 12, 1.0, 0.001);
+        for x in 3..=12 {
+            terrain.set(x, 10, 1.0);
+            terrain.set(x, 11, 1.0);
+        }
+        for y in 2..10 {
+            terrain.set(3, y, 1.0);
+            terrain.set(12, y, 1.0);
+        }
+        terrain
+    }
+
+    #[test]
+    pub fn test_water_settles_in_basin() {
+        let terrain = basin();
+        let settings = WaterSettings { min_amount: 0.0, ..WaterSettings::default() };
+        let mut layer = WaterLayer::new(&terrain, 0.1, settings);
+        layer.pour(&terrain, [7.5, 3.0], 1.0, 6.0);
+        let total = layer.total();
+        assert!(total > 0.0);
+
+        for _ in 0..2000 {
+            layer.step(&terrain);
+        }
+        assert!((layer.total() - total).abs() < 0.001);
+        // 12 units over 8 columns fill the bottom row and half of the one above
+        assert!((4..=11).all(|x| layer.water.get(x, 9) > 1.0 && layer.water.get(x, 8) > 0.45));
+        assert!((4..=11).all(|x| layer.water.get(x, 6) < 0.001));
+        let levels = (4..=11).map(|x| layer.water.get(x, 8)).collect::<Vec<_>>();
+        assert!(levels.iter().all(|it| (it - levels[0]).abs() < 0.05), "{:?}", levels);
+        assert!((0..16).all(|x| layer.water.get(x, 11) == 0.0 && layer.water.get(x, 10) == 0.0));
+
+        let contours = trace_contours(&layer.water, layer.water.cell_rect());
+        assert_eq!(1, contours.len());
+        assert!(contours[0].closed);
+    }
+
+    #[test]
+    pub fn test_water_falls_off_ledge() {
+        let mut terrain = WeightGrid::new(12, 10, 1.0, 0.001);
+        for x in 0..6 {
+            terrain.set(x, 4, 1.0);
+        }
+        let mut layer = WaterLayer::new(&terrain, 0.1, WaterSettings::default());
+        layer.pour(&terrain, [1.0, 3.0], 0.5, 2.0);
+        for _ in 0..500 {
+            layer.step(&terrain);
+        }
+        let on_ledge = (0..12).map(|x| (0..4).map(|y| layer.water.get(x, y)).sum::<f32>()).sum::<f32>();
+        let on_floor = (0..12).map(|x| layer.water.get(x, 9)).sum::<f32>();
+        assert!(on_ledge < 0.01 && on_floor > 1.0);
+
+        // water inside the solid disappears
+        terrain.set(8, 9, 1.0);
+        layer.step(&terrain);
+        assert_eq!(0.0, layer.water.get(8, 9));
+    }
+}
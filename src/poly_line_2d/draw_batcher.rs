@@ -5,6 +5,12 @@ pub struct BufferedDrawBatcher {
     index_buffer: Vec<u16>,
 }
 
+impl Default for BufferedDrawBatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BufferedDrawBatcher {
     pub fn new() -> Self {
         Self {
@@ -31,7 +37,7 @@ impl BufferedDrawBatcher {
     }
 
     pub fn renderize(&mut self, texture: Option<Texture2D>) {
-        if self.vertex_buffer.len() == 0 {
+        if self.vertex_buffer.is_empty() {
             self.clear_buffers();
             return;
         }
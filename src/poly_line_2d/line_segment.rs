@@ -15,7 +15,7 @@ pub struct LineSegment {
 }
 impl LineSegment {
     pub fn direction(&self, direction: Direction) -> Vector2<f32> {
-        let dir = self.b.clone() - self.a.clone();
+        let dir = self.b - self.a;
         match direction {
             Direction::Absolute => dir,
             Direction::Normalized => dir.normalize()
@@ -99,19 +99,22 @@ pub struct PolySegment {
     pub u_aa: LineSegment,
     pub u: LineSegment,
     pub l_aa: LineSegment,
-    pub l: LineSegment
+    pub l: LineSegment,
+    pub fringe: f32
 }
 
 impl PolySegment {
-    pub fn new(center: &LineSegment, thickness: f32) -> Self {
+    /// `fringe` is the width of the anti-aliased border in the same units as `center`
+    pub fn new(center: &LineSegment, thickness: f32, fringe: f32) -> Self {
         let nrm = center.normal();
         let half_thickness = thickness / 2.0;
         Self {
             c: center.clone(),
-            u_aa: center.clone() + nrm.clone() * (half_thickness + 1.0),
-            u: center.clone() + nrm.clone() * half_thickness,
-            l_aa: center.clone() - nrm.clone() * (half_thickness + 1.0),
-            l: center.clone() - nrm.clone() * half_thickness
+            u_aa: center.clone() + nrm * (half_thickness + fringe),
+            u: center.clone() + nrm * half_thickness,
+            l_aa: center.clone() - nrm * (half_thickness + fringe),
+            l: center.clone() - nrm * half_thickness,
+            fringe
         }
     }
 }
\ No newline at end of file
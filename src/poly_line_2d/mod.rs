@@ -2,13 +2,20 @@ pub mod line_segment;
 pub mod style;
 pub mod draw_batcher;
 pub mod parts;
+pub mod transform;
+pub mod path;
 
 use macroquad::prelude::*;
 use nalgebra::{Vector2, Vector3};
 use draw_batcher::BufferedDrawBatcher;
-use crate::poly_line_2d::style::{JointStyle, EndCapStyle, LineStripStyle};
+use crate::poly_line_2d::style::{JointStyle, EndCapStyle, LineStripStyle, ThicknessSpace, DashStyle};
+use crate::poly_line_2d::transform::Transform2D;
+use crate::poly_line_2d::path::{Path, split_dashes};
 use crate::poly_line_2d::parts::{CapSegment, VSegments, SegmentTriangulation, DoubleCapSegment};
 
+/// Flattening tolerance in pixels used for bezier strips
+const BEZIER_TOLERANCE: f32 = 0.25;
+
 pub fn cross(lhs: Vector2<f32>, rhs: Vector2<f32>) -> f32 {
     let lhs: Vector3<f32> = [lhs.x, lhs.y, 0.0].into();
     let rhs: Vector3<f32> = [rhs.x, lhs.y, 0.0].into();
@@ -18,16 +25,126 @@ pub fn cross(lhs: Vector2<f32>, rhs: Vector2<f32>) -> f32 {
 
 pub struct Painter {
     bezier_strip_buffer: Vec<[f32; 2]>,
+    path_buffer: Path,
     line_strip_buffer: Vec<[f32; 2]>,
-    draw_batcher: BufferedDrawBatcher
+    draw_batcher: BufferedDrawBatcher,
+    aa_fringe_width: f32,
+    view_scale: f32,
+    transform: Transform2D,
+    transform_stack: Vec<Transform2D>,
+    thickness_space: ThicknessSpace,
+    dash_style: DashStyle
+}
+
+impl Default for Painter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Painter {
     pub fn new() -> Self {
         Self {
             bezier_strip_buffer: Vec::new(),
+            path_buffer: Path::new(),
             line_strip_buffer: Vec::new(),
-            draw_batcher: BufferedDrawBatcher::new()
+            draw_batcher: BufferedDrawBatcher::new(),
+            aa_fringe_width: 1.0,
+            view_scale: 1.0,
+            transform: Transform2D::identity(),
+            transform_stack: Vec::new(),
+            thickness_space: ThicknessSpace::World,
+            dash_style: DashStyle::Solid
+        }
+    }
+
+    /// Saves the current transform so it can be restored with `pop_transform`
+    pub fn push_transform(&mut self) {
+        self.transform_stack.push(self.transform);
+    }
+
+    /// Restores the transform saved by the matching `push_transform`.
+    /// An unmatched pop is a bug, release builds reset to identity then
+    pub fn pop_transform(&mut self) {
+        debug_assert!(!self.transform_stack.is_empty(), "pop_transform without a matching push_transform");
+        self.transform = self.transform_stack.pop().unwrap_or_default();
+    }
+
+    pub fn transform(&self) -> Transform2D {
+        self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform2D) {
+        self.transform = transform;
+    }
+
+    /// Appends a transform which is applied to points before the current one
+    pub fn apply_transform(&mut self, transform: Transform2D) {
+        self.transform = self.transform * transform;
+    }
+
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.apply_transform(Transform2D::translation(x, y));
+    }
+
+    pub fn scale(&mut self, sx: f32, sy: f32) {
+        self.apply_transform(Transform2D::scaling(sx, sy));
+    }
+
+    pub fn rotate(&mut self, radians: f32) {
+        self.apply_transform(Transform2D::rotation(radians));
+    }
+
+    pub fn set_thickness_space(&mut self, thickness_space: ThicknessSpace) {
+        self.thickness_space = thickness_space;
+    }
+
+    pub fn thickness_space(&self) -> ThicknessSpace {
+        self.thickness_space
+    }
+
+    /// Sets the dash pattern used by all the following line strips and paths
+    pub fn set_dash_style(&mut self, dash_style: DashStyle) {
+        self.dash_style = dash_style;
+    }
+
+    pub fn dash_style(&self) -> DashStyle {
+        self.dash_style
+    }
+
+    /// Converts a stroke thickness to the space in which triangulation happens
+    fn transformed_thickness(&self, thickness: f32) -> f32 {
+        match self.thickness_space {
+            ThicknessSpace::World => thickness * self.transform.scale_factor(),
+            ThicknessSpace::Screen => thickness
+        }
+    }
+
+    /// Sets the width of the anti-aliased fringe in screen pixels
+    pub fn set_aa_fringe_width(&mut self, pixels: f32) {
+        self.aa_fringe_width = pixels.max(0.0);
+    }
+
+    pub fn aa_fringe_width(&self) -> f32 {
+        self.aa_fringe_width
+    }
+
+    /// Sets how many screen pixels one unit of the transformed coordinates takes,
+    /// e.g. the zoom of a macroquad camera which is applied after the painter transform
+    pub fn set_view_scale(&mut self, scale: f32) {
+        self.view_scale = scale;
+    }
+
+    pub fn view_scale(&self) -> f32 {
+        self.view_scale
+    }
+
+    /// The anti-aliased fringe width converted to the units in which triangulation happens
+    fn local_fringe(&self) -> f32 {
+        if self.view_scale.abs() < f32::EPSILON {
+            self.aa_fringe_width
+        } else {
+            self.aa_fringe_width / self.view_scale.abs()
         }
     }
 
@@ -42,6 +159,9 @@ impl Painter {
             return;
         }
         self.draw_batcher.clear_buffers();
+        let fringe = self.local_fringe();
+        let center = self.transform.apply(center);
+        let radius = radius * self.transform.scale_factor();
         let theta_delta = (360.0 / num_subdivs as f32).to_radians();
         for id in 0..num_subdivs {
             let theta = id as f32 * theta_delta;
@@ -69,18 +189,18 @@ impl Painter {
                             color
                         ),
                         Vertex::new(
-                            center[0] + cs * (radius + 1.0),
-                            center[1] + sn * (radius + 1.0),
+                            center[0] + cs * (radius + fringe),
+                            center[1] + sn * (radius + fringe),
                             0.0,
                             0.0, 0.0,
                             Color::new(color.r, color.g, color.b, 0.0)
                         )
-                    ].iter().map(|it| *it),
+                    ].iter().copied(),
                     [
                         0, left_1, right_1,
                         left_1, left_2, right_2,
                         left_1, right_2, right_1
-                    ].iter().map(|it| *it)
+                    ].iter().copied()
                 )
             } else {
                 self.draw_batcher.extend(
@@ -93,31 +213,56 @@ impl Painter {
                             color
                         ),
                         Vertex::new(
-                            center[0] + cs * (radius + 1.0),
-                            center[1] + sn * (radius + 1.0),
+                            center[0] + cs * (radius + fringe),
+                            center[1] + sn * (radius + fringe),
                             0.0,
                             0.0, 0.0,
                             Color::new(color.r, color.g, color.b, 0.0)
                         )
-                    ].iter().map(|it| *it),
+                    ].iter().copied(),
                     [
                         0, left_1, right_1,
                         left_1, left_2, right_2,
                         left_1, right_2, right_1
-                    ].iter().map(|it| *it)
+                    ].iter().copied()
                 )
             }
         }
         self.draw_batcher.renderize(None);
     }
 
+    /// Fills convex polygons without anti-aliasing, meant to sit behind an outline
+    pub fn fill_convex_polygons(&mut self, color: Color, polygons: impl Iterator<Item = Vec<[f32; 2]>>) {
+        self.draw_batcher.clear_buffers();
+        let mut first_id = 0u16;
+        for polygon in polygons {
+            if polygon.len() < 3 {
+                continue;
+            }
+            if self.draw_batcher.too_many_vertices_in_buffer() {
+                self.draw_batcher.renderize(None);
+                first_id = 0;
+            }
+            let transform = self.transform;
+            self.draw_batcher.extend(
+                polygon.iter().map(|it| {
+                    let [x, y] = transform.apply(*it);
+                    Vertex::new(x, y, 0.0, 0.0, 0.0, color)
+                }),
+                (1..polygon.len() as u16 - 1).flat_map(|id| [first_id, first_id + id, first_id + id + 1])
+            );
+            first_id += polygon.len() as u16;
+        }
+        self.draw_batcher.renderize(None);
+    }
+
     pub fn draw_square_bezier_strip(
         &mut self,
         color: Color,
         thickness: f32,
         points: &[[f32; 2]]
     ) {
-        self.draw_square_bezier_strip_ex(color, thickness, points.iter().map(|it| *it));
+        self.draw_square_bezier_strip_ex(color, thickness, points.iter().copied());
     }
 
     pub fn draw_square_bezier_strip_ex(
@@ -128,55 +273,64 @@ impl Painter {
     ) {
         self.bezier_strip_buffer.clear();
         self.bezier_strip_buffer.extend(points);
+        if self.bezier_strip_buffer.len() < 3 {
+            return;
+        }
 
+        self.path_buffer.clear();
+        self.path_buffer.move_to(self.bezier_strip_buffer[0]);
         let mut offset = 0;
-        loop {
-            if offset + 2 >= self.bezier_strip_buffer.len() {
-                break;
-            }
-            let a = self.bezier_strip_buffer[offset];
+        while offset + 2 < self.bezier_strip_buffer.len() {
             let control = self.bezier_strip_buffer[offset + 1];
             let b = self.bezier_strip_buffer[offset + 2];
-
-            self.draw_square_bezier(a, b, control, color, thickness, 15);
-
+            self.path_buffer.quad_to(control, b);
             offset += 2;
         }
-    }
 
-    fn draw_square_bezier(
-        &mut self,
-        a: [f32; 2],
-        b: [f32; 2],
-        control: [f32; 2],
-        color: Color,
-        thickness: f32,
-        num_subdivs: usize
-    ) {
-        let dt = 1.0 / num_subdivs as f32;
-        self.draw_lines_ex(
+        let path = std::mem::take(&mut self.path_buffer);
+        self.draw_path(
+            &path,
             JointStyle::Bevel,
             EndCapStyle::Square,
-            LineStripStyle::Open,
             color,
             thickness,
-            (0..num_subdivs+1)
-                .map(|ix|{
-                    let t = (ix as f32 * dt).clamp(0.0, 1.0);
-                    let t_opposite = 1.0 - t;
-
-                    let xx1 = a[0] * t_opposite + control[0] * t;
-                    let xx2 = b[0] * t + control[0] * t_opposite;
-
-                    let yy1 = a[1] * t_opposite + control[1] * t;
-                    let yy2 = b[1] * t + control[1] * t_opposite;
-
-                    [
-                        xx1 * t_opposite + xx2 * t,
-                        yy1 * t_opposite + yy2 * t,
-                    ]
-                })
+            BEZIER_TOLERANCE
         );
+        self.path_buffer = path;
+    }
+
+    /// Strokes every subpath of `path` as a single strip.
+    /// `tolerance` is the maximal deviation of flattened curves in screen pixels
+    pub fn draw_path(
+        &mut self,
+        path: &Path,
+        joint_style: JointStyle,
+        end_cap_style: EndCapStyle,
+        color: Color,
+        thickness: f32,
+        tolerance: f32
+    ) {
+        let pixels_per_unit = self.transform.scale_factor() * self.view_scale.abs();
+        let tolerance = if pixels_per_unit < f32::EPSILON {
+            tolerance
+        } else {
+            tolerance / pixels_per_unit
+        };
+        for subpath in path.flatten(tolerance) {
+            let line_strip_style = if subpath.closed {
+                LineStripStyle::Closed
+            } else {
+                LineStripStyle::Open
+            };
+            self.draw_lines(
+                joint_style,
+                end_cap_style,
+                line_strip_style,
+                color,
+                thickness,
+                &subpath.points
+            );
+        }
     }
 
     pub fn draw_lines(
@@ -194,7 +348,7 @@ impl Painter {
             line_strip_style,
             color,
             thickness,
-            points.iter().map(|it| *it)
+            points.iter().copied()
         )
     }
 
@@ -208,13 +362,48 @@ impl Painter {
         points: impl Iterator<Item = [f32; 2]>
     ) {
         self.line_strip_buffer.clear();
-        self.line_strip_buffer.extend(points);
+        let transform = self.transform;
+        self.line_strip_buffer.extend(points.map(|it| transform.apply(it)));
+        let thickness = self.transformed_thickness(thickness);
 
+        match self.dash_style {
+            DashStyle::Solid => {
+                self.stroke_line_strip_buffer(joint_style, end_cap_style, line_strip_style, color, thickness);
+            }
+            DashStyle::Dashed { dash, gap, offset } => {
+                let line_strip = std::mem::take(&mut self.line_strip_buffer);
+                let dashes = split_dashes(
+                    &line_strip,
+                    matches!(line_strip_style, LineStripStyle::Closed),
+                    self.transformed_thickness(dash),
+                    self.transformed_thickness(gap),
+                    self.transformed_thickness(offset)
+                );
+                for dash in dashes {
+                    self.line_strip_buffer.clear();
+                    self.line_strip_buffer.extend_from_slice(&dash);
+                    self.stroke_line_strip_buffer(joint_style, end_cap_style, LineStripStyle::Open, color, thickness);
+                }
+                self.line_strip_buffer = line_strip;
+            }
+        }
+    }
+
+    /// Triangulates and draws the already transformed points of `line_strip_buffer`
+    fn stroke_line_strip_buffer(
+        &mut self,
+        joint_style: JointStyle,
+        end_cap_style: EndCapStyle,
+        line_strip_style: LineStripStyle,
+        color: Color,
+        thickness: f32
+    ) {
         let length = self.line_strip_buffer.len();
         if length <= 1 {
             return; // for lines we need at least two points
         }
         self.draw_batcher.clear_buffers();
+        let fringe = self.local_fringe();
 
         if length == 2 {
             let seg = DoubleCapSegment::new(
@@ -226,11 +415,17 @@ impl Painter {
                     self.line_strip_buffer[1][0],
                     self.line_strip_buffer[1][1]
                 ].into(),
-                thickness
+                thickness,
+                fringe
             );
             seg.triangulate(0, color, end_cap_style)
                 .extend_draw_batcher(&mut self.draw_batcher);
             self.draw_batcher.renderize(None);
+            if let EndCapStyle::Round = end_cap_style {
+                let (a, b) = (self.line_strip_buffer[0], self.line_strip_buffer[1]);
+                self.draw_round_cap(a, b, thickness / 2.0, color);
+                self.draw_round_cap(b, a, thickness / 2.0, color);
+            }
             return;
         }
 
@@ -249,7 +444,7 @@ impl Painter {
                     (self.line_strip_buffer[0][0] + self.line_strip_buffer[last_id][0]) / 2.0,
                     (self.line_strip_buffer[0][1] + self.line_strip_buffer[last_id][1]) / 2.0
                 ].into();
-                let v_segments = VSegments::new(a, b, c, thickness);
+                let v_segments = VSegments::new(a, b, c, thickness, fringe);
                 let joint_style = match joint_style {
                     JointStyle::Miter if v_segments.angle_is_too_sharp() => JointStyle::Bevel,
                     _ => joint_style
@@ -270,7 +465,7 @@ impl Painter {
                     (self.line_strip_buffer[0][0] + self.line_strip_buffer[last_id][0]) / 2.0,
                     (self.line_strip_buffer[0][1] + self.line_strip_buffer[last_id][1]) / 2.0
                 ].into();
-                let v_segments = VSegments::new(a, b, c, thickness);
+                let v_segments = VSegments::new(a, b, c, thickness, fringe);
                 let joint_style = match joint_style {
                     JointStyle::Miter if v_segments.angle_is_too_sharp() => JointStyle::Bevel,
                     _ => joint_style
@@ -289,7 +484,8 @@ impl Painter {
                         self.line_strip_buffer[0][0],
                         self.line_strip_buffer[0][1]
                     ].into(),
-                    thickness
+                    thickness,
+                    fringe
                 );
                 let cap_segment_end = CapSegment::new(
                     [
@@ -300,7 +496,8 @@ impl Painter {
                         self.line_strip_buffer[length-1][0],
                         self.line_strip_buffer[length-1][1]
                     ].into(),
-                    thickness
+                    thickness,
+                    fringe
                 );
                 cap_segment_start.triangulate(0, color, end_cap_style)
                     .extend_draw_batcher(&mut self.draw_batcher);
@@ -309,6 +506,13 @@ impl Painter {
                 cap_segment_end.triangulate(0, color, end_cap_style)
                     .extend_draw_batcher(&mut self.draw_batcher);
                 self.draw_batcher.renderize(None);
+
+                if let EndCapStyle::Round = end_cap_style {
+                    let (first, second) = (self.line_strip_buffer[0], self.line_strip_buffer[1]);
+                    let (last, before_last) = (self.line_strip_buffer[length - 1], self.line_strip_buffer[length - 2]);
+                    self.draw_round_cap(first, second, thickness / 2.0, color);
+                    self.draw_round_cap(last, before_last, thickness / 2.0, color);
+                }
             }
         }
 
@@ -325,7 +529,7 @@ impl Painter {
                 (self.line_strip_buffer[i + 1][0] + self.line_strip_buffer[i + 2][0]) / 2.0,
                 (self.line_strip_buffer[i + 1][1] + self.line_strip_buffer[i + 2][1]) / 2.0
             ].into();
-            let v_segments = VSegments::new(a, b, c, thickness);
+            let v_segments = VSegments::new(a, b, c, thickness, fringe);
 
             let joint_style = match joint_style {
                 JointStyle::Miter if v_segments.angle_is_too_sharp() => JointStyle::Bevel,
@@ -341,7 +545,7 @@ impl Painter {
                             0.0, 0.0,
                             Color::new(it.color.0, it.color.1, it.color.2, it.color.3)
                         )),
-                        indices.iter().map(|it| *it)
+                        indices.iter().copied()
                     );
                 }
                 SegmentTriangulation::Miter { vertices, indices } => {
@@ -351,7 +555,7 @@ impl Painter {
                             0.0, 0.0,
                             Color::new(it.color.0, it.color.1, it.color.2, it.color.3)
                         )),
-                        indices.iter().map(|it| *it)
+                        indices.iter().copied()
                     );
                 }
                 SegmentTriangulation::Bevel { vertices, indices } => {
@@ -361,7 +565,7 @@ impl Painter {
                             0.0, 0.0,
                             Color::new(it.color.0, it.color.1, it.color.2, it.color.3)
                         )),
-                        indices.iter().map(|it| *it)
+                        indices.iter().copied()
                     );
                 }
             }
@@ -370,4 +574,91 @@ impl Painter {
 
         self.draw_batcher.renderize(None);
     }
-}
\ No newline at end of file
+
+    /// Anti-aliased half-disc around the already transformed `center`, facing away from `inner`
+    fn draw_round_cap(&mut self, center: [f32; 2], inner: [f32; 2], radius: f32, color: Color) {
+        let (dx, dy) = (center[0] - inner[0], center[1] - inner[1]);
+        let length = (dx * dx + dy * dy).sqrt();
+        if length < f32::EPSILON {
+            return;
+        }
+        // from the left side of the line around the end to its right side
+        let start = dx.atan2(-dy);
+        let fringe = self.local_fringe();
+        let pixel_radius = radius * self.view_scale.abs();
+        let num_subdivs = (pixel_radius.sqrt() * 2.0).ceil().clamp(2.0, 32.0) as u16;
+        let transparent = Color::new(color.r, color.g, color.b, 0.0);
+
+        self.draw_batcher.clear_buffers();
+        self.draw_batcher.extend(
+            std::iter::once(Vertex::new(center[0], center[1], 0.0, 0.0, 0.0, color)),
+            std::iter::empty()
+        );
+        for id in 0..=num_subdivs {
+            let theta = start - id as f32 / num_subdivs as f32 * std::f32::consts::PI;
+            let (cs, sn) = (theta.cos(), theta.sin());
+            self.draw_batcher.extend(
+                [
+                    Vertex::new(center[0] + cs * radius, center[1] + sn * radius, 0.0, 0.0, 0.0, color),
+                    Vertex::new(
+                        center[0] + cs * (radius + fringe),
+                        center[1] + sn * (radius + fringe),
+                        0.0,
+                        0.0, 0.0,
+                        transparent
+                    )
+                ].iter().copied(),
+                std::iter::empty()
+            );
+            if id > 0 {
+                let (left_1, left_2) = (id * 2 - 1, id * 2);
+                let (right_1, right_2) = (id * 2 + 1, id * 2 + 2);
+                self.draw_batcher.extend(
+                    std::iter::empty(),
+                    [
+                        0, left_1, right_1,
+                        left_1, left_2, right_2,
+                        left_1, right_2, right_1
+                    ].iter().copied()
+                );
+            }
+        }
+        self.draw_batcher.renderize(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poly_line_2d::Painter;
+    use crate::poly_line_2d::style::ThicknessSpace;
+
+    #[test]
+    pub fn test_fringe_follows_view_scale() {
+        let mut painter = Painter::new();
+        painter.set_aa_fringe_width(2.0);
+        assert!((painter.local_fringe() - 2.0).abs() < f32::EPSILON);
+
+        painter.set_view_scale(4.0);
+        assert!((painter.local_fringe() - 0.5).abs() < f32::EPSILON);
+
+        painter.set_view_scale(0.25);
+        assert!((painter.local_fringe() - 8.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    pub fn test_transform_stack() {
+        let mut painter = Painter::new();
+        painter.push_transform();
+        painter.scale(2.0, 2.0);
+        painter.translate(-10.0, -20.0);
+        assert_eq!(painter.transform().apply([10.0, 20.0]), [0.0, 0.0]);
+        assert_eq!(painter.transform().apply([11.0, 20.0]), [2.0, 0.0]);
+
+        assert!((painter.transformed_thickness(3.0) - 6.0).abs() < f32::EPSILON);
+        painter.set_thickness_space(ThicknessSpace::Screen);
+        assert!((painter.transformed_thickness(3.0) - 3.0).abs() < f32::EPSILON);
+
+        painter.pop_transform();
+        assert_eq!(painter.transform().apply([11.0, 20.0]), [11.0, 20.0]);
+    }
+}
@@ -21,6 +21,15 @@ impl VertexData {
     ) -> Self { Self { pos_x, pos_y, color } }
 }
 
+/// Color of the outer edge of the anti-aliased fringe. It fades to zero alpha like the fringe of
+/// `Painter::draw_circle`; an opaque outer edge would only widen the line by the fringe width
+fn fringe_color(color: Color) -> (f32, f32, f32, f32) {
+    (color.r, color.g, color.b, 0.0)
+}
+
+/// Joint points of the upper, upper AA, lower and lower AA outlines
+type OutlineIntersection = (Point2<f32>, Point2<f32>, Point2<f32>, Point2<f32>);
+
 pub(crate) struct VSegments {
     lcs: PolySegment,
     rcs: PolySegment,
@@ -46,7 +55,7 @@ impl SegmentTriangulation {
                         0.0, 0.0,
                         Color::new(it.color.0, it.color.1, it.color.2, it.color.3),
                     )),
-                    indices.iter().map(|it| *it),
+                    indices.iter().copied(),
                 );
             }
             SegmentTriangulation::Miter { vertices, indices } => {
@@ -56,7 +65,7 @@ impl SegmentTriangulation {
                         0.0, 0.0,
                         Color::new(it.color.0, it.color.1, it.color.2, it.color.3),
                     )),
-                    indices.iter().map(|it| *it),
+                    indices.iter().copied(),
                 );
             }
             SegmentTriangulation::Bevel { vertices, indices } => {
@@ -66,7 +75,7 @@ impl SegmentTriangulation {
                         0.0, 0.0,
                         Color::new(it.color.0, it.color.1, it.color.2, it.color.3),
                     )),
-                    indices.iter().map(|it| *it),
+                    indices.iter().copied(),
                 );
             }
         }
@@ -74,18 +83,12 @@ impl SegmentTriangulation {
 }
 
 impl VSegments {
-    pub(crate) fn new(a: Point2<f32>, b: Point2<f32>, c: Point2<f32>, thickness: f32) -> Self {
-        let line_segment_first = LineSegment {
-            a: a.clone(),
-            b: b.clone(),
-        };
-        let line_segment_second = LineSegment {
-            a: b.clone(),
-            b: c.clone(),
-        };
+    pub(crate) fn new(a: Point2<f32>, b: Point2<f32>, c: Point2<f32>, thickness: f32, fringe: f32) -> Self {
+        let line_segment_first = LineSegment { a, b };
+        let line_segment_second = LineSegment { a: b, b: c };
         Self {
-            lcs: PolySegment::new(&line_segment_first, thickness),
-            rcs: PolySegment::new(&line_segment_second, thickness),
+            lcs: PolySegment::new(&line_segment_first, thickness, fringe),
+            rcs: PolySegment::new(&line_segment_second, thickness, fringe),
         }
     }
 
@@ -101,7 +104,7 @@ impl VSegments {
         dir1.dot(&dir2) < -0.8
     }
 
-    fn get_intersection(&self) -> Option<(Point2<f32>, Point2<f32>, Point2<f32>, Point2<f32>)> {
+    fn get_intersection(&self) -> Option<OutlineIntersection> {
         let upper = self.lcs.u
             .intersection(
                 self.rcs.u.clone()
@@ -122,7 +125,7 @@ impl VSegments {
     }
 
     pub(crate) fn triangulate(&self, start_id: u16, color: Color, style: JointStyle) -> SegmentTriangulation {
-        let transparent_color = (color.r, color.g, color.b, color.a);
+        let transparent_color = fringe_color(color);
         let color = (color.r, color.g, color.b, color.a);
         match self.get_intersection() {
             None => SegmentTriangulation::Straight {
@@ -252,13 +255,13 @@ impl VSegments {
 }
 
 impl CapSegment {
-    pub(crate) fn new(a: Point2<f32>, b: Point2<f32>, thickness: f32) -> Self {
+    pub(crate) fn new(a: Point2<f32>, b: Point2<f32>, thickness: f32, fringe: f32) -> Self {
         let line_segment = LineSegment { a, b };
-        Self(PolySegment::new(&line_segment, thickness), thickness / 2.0)
+        Self(PolySegment::new(&line_segment, thickness, fringe), thickness / 2.0)
     }
 
     pub(crate) fn triangulate(&self, start_id: u16, color: Color, style: EndCapStyle) -> SegmentTriangulation {
-        let transparent_color = (color.r, color.g, color.b, color.a);
+        let transparent_color = fringe_color(color);
         let color = (color.r, color.g, color.b, color.a);
         let dir_norm = self.0.c.direction(Direction::Normalized);
         match style {
@@ -270,15 +273,15 @@ impl CapSegment {
                     VertexData::new(self.0.l_aa.a.x, self.0.l_aa.a.y, transparent_color),
 
                     VertexData::new(
-                        self.0.u_aa.b.x + dir_norm.x,
-                        self.0.u_aa.b.y + dir_norm.y,
+                        self.0.u_aa.b.x + dir_norm.x * self.0.fringe,
+                        self.0.u_aa.b.y + dir_norm.y * self.0.fringe,
                         transparent_color
                     ),
                     VertexData::new(self.0.u.b.x, self.0.u.b.y, color),
                     VertexData::new(self.0.l.b.x, self.0.l.b.y, color),
                     VertexData::new(
-                        self.0.l_aa.b.x + dir_norm.x,
-                        self.0.l_aa.b.y + dir_norm.y,
+                        self.0.l_aa.b.x + dir_norm.x * self.0.fringe,
+                        self.0.l_aa.b.y + dir_norm.y * self.0.fringe,
                         transparent_color,
                     ),
                 ],
@@ -290,6 +293,27 @@ impl CapSegment {
                     * 4 5 6 7;
                 },
             },
+            EndCapStyle::Round => SegmentTriangulation::Straight {
+                // the end stays flat, the painter closes it with a half-disc
+                vertices: [
+                    VertexData::new(self.0.u_aa.a.x, self.0.u_aa.a.y, transparent_color),
+                    VertexData::new(self.0.u.a.x, self.0.u.a.y, color),
+                    VertexData::new(self.0.l.a.x, self.0.l.a.y, color),
+                    VertexData::new(self.0.l_aa.a.x, self.0.l_aa.a.y, transparent_color),
+
+                    VertexData::new(self.0.u_aa.b.x, self.0.u_aa.b.y, transparent_color),
+                    VertexData::new(self.0.u.b.x, self.0.u.b.y, color),
+                    VertexData::new(self.0.l.b.x, self.0.l.b.y, color),
+                    VertexData::new(self.0.l_aa.b.x, self.0.l_aa.b.y, transparent_color),
+                ],
+                indices: make_indices! {
+                    * 0 4 1 5;
+                    * 1 5 2 6;
+                    * 2 6 3 7;
+                    * 0 1 2 3;
+                    * 4 5 6 7;
+                },
+            },
             EndCapStyle::Square => SegmentTriangulation::Straight {
                 vertices: [
                     VertexData::new(self.0.u_aa.a.x, self.0.u_aa.a.y, transparent_color),
@@ -298,8 +322,8 @@ impl CapSegment {
                     VertexData::new(self.0.l_aa.a.x, self.0.l_aa.a.y, transparent_color),
 
                     VertexData::new(
-                        self.0.u_aa.b.x + dir_norm.x * (self.1 + 1.0),
-                        self.0.u_aa.b.y + dir_norm.y * (self.1 + 1.0),
+                        self.0.u_aa.b.x + dir_norm.x * (self.1 + self.0.fringe),
+                        self.0.u_aa.b.y + dir_norm.y * (self.1 + self.0.fringe),
                         transparent_color
                     ),
                     VertexData::new(
@@ -313,8 +337,8 @@ impl CapSegment {
                         color
                     ),
                     VertexData::new(
-                        self.0.l_aa.b.x + dir_norm.x * (self.1 + 1.0),
-                        self.0.l_aa.b.y + dir_norm.y * (self.1 + 1.0),
+                        self.0.l_aa.b.x + dir_norm.x * (self.1 + self.0.fringe),
+                        self.0.l_aa.b.y + dir_norm.y * (self.1 + self.0.fringe),
                         transparent_color
                     ),
                 ],
@@ -331,40 +355,40 @@ impl CapSegment {
 }
 
 impl DoubleCapSegment {
-    pub(crate) fn new(a: Point2<f32>, b: Point2<f32>, thickness: f32) -> Self {
+    pub(crate) fn new(a: Point2<f32>, b: Point2<f32>, thickness: f32, fringe: f32) -> Self {
         let line_segment = LineSegment { a, b };
-        Self(PolySegment::new(&line_segment, thickness), thickness / 2.0)
+        Self(PolySegment::new(&line_segment, thickness, fringe), thickness / 2.0)
     }
 
     pub(crate) fn triangulate(&self, start_id: u16, color: Color, style: EndCapStyle) -> SegmentTriangulation {
-        let transparent_color = (color.r, color.g, color.b, color.a);
+        let transparent_color = fringe_color(color);
         let color = (color.r, color.g, color.b, color.a);
         let dir_norm = self.0.c.direction(Direction::Normalized);
         match style {
             EndCapStyle::Butt => SegmentTriangulation::Straight {
                 vertices: [
                     VertexData::new(
-                        self.0.u_aa.a.x - dir_norm.x,
-                        self.0.u_aa.a.y - dir_norm.y,
+                        self.0.u_aa.a.x - dir_norm.x * self.0.fringe,
+                        self.0.u_aa.a.y - dir_norm.y * self.0.fringe,
                         transparent_color,
                     ),
                     VertexData::new(self.0.u.a.x, self.0.u.a.y, color),
                     VertexData::new(self.0.l.a.x, self.0.l.a.y, color),
                     VertexData::new(
-                        self.0.l_aa.a.x - dir_norm.x,
-                        self.0.l_aa.a.y - dir_norm.y,
+                        self.0.l_aa.a.x - dir_norm.x * self.0.fringe,
+                        self.0.l_aa.a.y - dir_norm.y * self.0.fringe,
                         transparent_color,
                     ),
                     VertexData::new(
-                        self.0.u_aa.b.x + dir_norm.x,
-                        self.0.u_aa.b.y + dir_norm.y,
+                        self.0.u_aa.b.x + dir_norm.x * self.0.fringe,
+                        self.0.u_aa.b.y + dir_norm.y * self.0.fringe,
                         transparent_color,
                     ),
                     VertexData::new(self.0.u.b.x, self.0.u.b.y, color),
                     VertexData::new(self.0.l.b.x, self.0.l.b.y, color),
                     VertexData::new(
-                        self.0.l_aa.b.x + dir_norm.x,
-                        self.0.l_aa.b.y + dir_norm.y,
+                        self.0.l_aa.b.x + dir_norm.x * self.0.fringe,
+                        self.0.l_aa.b.y + dir_norm.y * self.0.fringe,
                         transparent_color,
                     )
                 ],
@@ -376,11 +400,32 @@ impl DoubleCapSegment {
                     * 4 5 6 7;
                 },
             },
+            EndCapStyle::Round => SegmentTriangulation::Straight {
+                // both ends stay flat, the painter closes them with half-discs
+                vertices: [
+                    VertexData::new(self.0.u_aa.a.x, self.0.u_aa.a.y, transparent_color),
+                    VertexData::new(self.0.u.a.x, self.0.u.a.y, color),
+                    VertexData::new(self.0.l.a.x, self.0.l.a.y, color),
+                    VertexData::new(self.0.l_aa.a.x, self.0.l_aa.a.y, transparent_color),
+
+                    VertexData::new(self.0.u_aa.b.x, self.0.u_aa.b.y, transparent_color),
+                    VertexData::new(self.0.u.b.x, self.0.u.b.y, color),
+                    VertexData::new(self.0.l.b.x, self.0.l.b.y, color),
+                    VertexData::new(self.0.l_aa.b.x, self.0.l_aa.b.y, transparent_color),
+                ],
+                indices: make_indices! {
+                    * 0 4 1 5;
+                    * 1 5 2 6;
+                    * 2 6 3 7;
+                    * 0 1 2 3;
+                    * 4 5 6 7;
+                },
+            },
             EndCapStyle::Square => SegmentTriangulation::Straight {
                 vertices: [
                     VertexData::new(
-                        self.0.u_aa.a.x - dir_norm.x * (self.1 + 1.0),
-                        self.0.u_aa.a.y - dir_norm.y * (self.1 + 1.0),
+                        self.0.u_aa.a.x - dir_norm.x * (self.1 + self.0.fringe),
+                        self.0.u_aa.a.y - dir_norm.y * (self.1 + self.0.fringe),
                         transparent_color,
                     ),
                     VertexData::new(
@@ -394,13 +439,13 @@ impl DoubleCapSegment {
                         color,
                     ),
                     VertexData::new(
-                        self.0.l_aa.a.x - dir_norm.x * (self.1 + 1.0),
-                        self.0.l_aa.a.y - dir_norm.y * (self.1 + 1.0),
+                        self.0.l_aa.a.x - dir_norm.x * (self.1 + self.0.fringe),
+                        self.0.l_aa.a.y - dir_norm.y * (self.1 + self.0.fringe),
                         transparent_color,
                     ),
                     VertexData::new(
-                        self.0.u_aa.b.x + dir_norm.x * (self.1 + 1.0),
-                        self.0.u_aa.b.y + dir_norm.y * (self.1 + 1.0),
+                        self.0.u_aa.b.x + dir_norm.x * (self.1 + self.0.fringe),
+                        self.0.u_aa.b.y + dir_norm.y * (self.1 + self.0.fringe),
                         transparent_color,
                     ),
                     VertexData::new(
@@ -414,8 +459,8 @@ impl DoubleCapSegment {
                         color,
                     ),
                     VertexData::new(
-                        self.0.l_aa.b.x + dir_norm.x * (self.1 + 1.0),
-                        self.0.l_aa.b.y + dir_norm.y * (self.1 + 1.0),
+                        self.0.l_aa.b.x + dir_norm.x * (self.1 + self.0.fringe),
+                        self.0.l_aa.b.y + dir_norm.y * (self.1 + self.0.fringe),
                         transparent_color,
                     ),
                 ],
@@ -442,15 +487,17 @@ mod tests {
             [0.0, 100.0].into(),
             [100.0, 85.0].into(),
             8.0,
+            1.0,
         );
-        assert_eq!(true, v_segments.is_clockwise());
+        assert!(v_segments.is_clockwise());
 
         let v_segments = VSegments::new(
             [0.0, 0.0].into(),
             [0.0, 100.0].into(),
             [-100.0, 85.0].into(),
             8.0,
+            1.0,
         );
-        assert_eq!(false, v_segments.is_clockwise());
+        assert!(!v_segments.is_clockwise());
     }
 }
\ No newline at end of file
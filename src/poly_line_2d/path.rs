@@ -0,0 +1,333 @@
+#[derive(Copy, Clone)]
+enum PathCommand {
+    MoveTo([f32; 2]),
+    LineTo([f32; 2]),
+    QuadTo { control: [f32; 2], to: [f32; 2] },
+    CubicTo { control_1: [f32; 2], control_2: [f32; 2], to: [f32; 2] },
+    ArcAround { center: [f32; 2], sweep: f32 },
+    Close
+}
+
+/// One continuous piece of a flattened path
+pub struct FlatSubpath {
+    pub points: Vec<[f32; 2]>,
+    pub closed: bool
+}
+
+/// Vector path made of lines and curves which is flattened into polylines for stroking
+#[derive(Clone, Default)]
+pub struct Path {
+    commands: Vec<PathCommand>
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self { commands: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Starts a new subpath at `to`
+    pub fn move_to(&mut self, to: [f32; 2]) -> &mut Self {
+        self.commands.push(PathCommand::MoveTo(to));
+        self
+    }
+
+    pub fn line_to(&mut self, to: [f32; 2]) -> &mut Self {
+        self.commands.push(PathCommand::LineTo(to));
+        self
+    }
+
+    /// Quadratic Bézier curve from the current point to `to`
+    pub fn quad_to(&mut self, control: [f32; 2], to: [f32; 2]) -> &mut Self {
+        self.commands.push(PathCommand::QuadTo { control, to });
+        self
+    }
+
+    /// Cubic Bézier curve from the current point to `to`
+    pub fn cubic_to(&mut self, control_1: [f32; 2], control_2: [f32; 2], to: [f32; 2]) -> &mut Self {
+        self.commands.push(PathCommand::CubicTo { control_1, control_2, to });
+        self
+    }
+
+    /// Circular arc around `center` starting at the current point.
+    /// Positive `sweep` (in radians) goes from +X towards +Y
+    pub fn arc_around(&mut self, center: [f32; 2], sweep: f32) -> &mut Self {
+        self.commands.push(PathCommand::ArcAround { center, sweep });
+        self
+    }
+
+    /// Connects the current point back to the start of the subpath
+    pub fn close(&mut self) -> &mut Self {
+        self.commands.push(PathCommand::Close);
+        self
+    }
+
+    /// Converts the path into polylines which deviate from the curves by at most `tolerance`
+    pub fn flatten(&self, tolerance: f32) -> Vec<FlatSubpath> {
+        let tolerance = tolerance.max(0.001);
+        let mut result = Vec::new();
+        let mut current = FlatSubpath { points: Vec::new(), closed: false };
+        let mut cursor = [0.0, 0.0];
+
+        for command in self.commands.iter() {
+            match *command {
+                PathCommand::MoveTo(to) => {
+                    finish_subpath(&mut result, &mut current);
+                    push_point(&mut current.points, to);
+                    cursor = to;
+                }
+                PathCommand::LineTo(to) => {
+                    push_point(&mut current.points, cursor);
+                    push_point(&mut current.points, to);
+                    cursor = to;
+                }
+                PathCommand::QuadTo { control, to } => {
+                    push_point(&mut current.points, cursor);
+                    let dd = length([
+                        cursor[0] - 2.0 * control[0] + to[0],
+                        cursor[1] - 2.0 * control[1] + to[1]
+                    ]);
+                    let num_subdivs = subdivision_count((dd / (4.0 * tolerance)).sqrt());
+                    for ix in 1..=num_subdivs {
+                        let t = ix as f32 / num_subdivs as f32;
+                        push_point(&mut current.points, quad_point(cursor, control, to, t));
+                    }
+                    cursor = to;
+                }
+                PathCommand::CubicTo { control_1, control_2, to } => {
+                    push_point(&mut current.points, cursor);
+                    let dd = length([
+                        cursor[0] - 2.0 * control_1[0] + control_2[0],
+                        cursor[1] - 2.0 * control_1[1] + control_2[1]
+                    ]).max(length([
+                        control_1[0] - 2.0 * control_2[0] + to[0],
+                        control_1[1] - 2.0 * control_2[1] + to[1]
+                    ]));
+                    let num_subdivs = subdivision_count((3.0 * dd / (4.0 * tolerance)).sqrt());
+                    for ix in 1..=num_subdivs {
+                        let t = ix as f32 / num_subdivs as f32;
+                        push_point(&mut current.points, cubic_point(cursor, control_1, control_2, to, t));
+                    }
+                    cursor = to;
+                }
+                PathCommand::ArcAround { center, sweep } => {
+                    push_point(&mut current.points, cursor);
+                    let radius = length([cursor[0] - center[0], cursor[1] - center[1]]);
+                    if radius <= f32::EPSILON {
+                        continue;
+                    }
+                    let start_angle = (cursor[1] - center[1]).atan2(cursor[0] - center[0]);
+                    let max_step = if tolerance >= radius {
+                        std::f32::consts::FRAC_PI_2
+                    } else {
+                        2.0 * (1.0 - tolerance / radius).acos()
+                    };
+                    let num_subdivs = subdivision_count(sweep.abs() / max_step.max(0.001));
+                    for ix in 1..=num_subdivs {
+                        let theta = start_angle + sweep * ix as f32 / num_subdivs as f32;
+                        push_point(
+                            &mut current.points,
+                            [center[0] + theta.cos() * radius, center[1] + theta.sin() * radius]
+                        );
+                    }
+                    cursor = *current.points.last().unwrap();
+                }
+                PathCommand::Close => {
+                    if let Some(&first) = current.points.first() {
+                        current.closed = true;
+                        finish_subpath(&mut result, &mut current);
+                        push_point(&mut current.points, first);
+                        cursor = first;
+                    }
+                }
+            }
+        }
+        finish_subpath(&mut result, &mut current);
+        result
+    }
+}
+
+/// Splits a polyline into dashes `dash` long separated by `gap` long holes.
+/// The pattern starts `offset` into the first dash, so lines cut from a longer one can keep its phase.
+/// A dash running over a vertex keeps that vertex, so it can be stroked with a joint
+pub fn split_dashes(points: &[[f32; 2]], closed: bool, dash: f32, gap: f32, offset: f32) -> Vec<Vec<[f32; 2]>> {
+    if dash <= 0.0 || points.len() < 2 {
+        return Vec::new();
+    }
+    let mut vertices = points.to_vec();
+    if closed {
+        vertices.push(points[0]);
+    }
+    if gap <= 0.0 {
+        return vec![vertices];
+    }
+
+    let period = dash + gap;
+    let phase = offset.rem_euclid(period);
+    let mut in_dash = phase < dash;
+    let mut remaining = if in_dash { dash - phase } else { period - phase };
+
+    let mut dashes = Vec::new();
+    let mut current = Vec::new();
+    for pair in vertices.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let segment_length = length([b[0] - a[0], b[1] - a[1]]);
+        if in_dash && current.is_empty() {
+            current.push(a);
+        }
+        let mut position = 0.0;
+        while segment_length - position > remaining {
+            position += remaining;
+            let t = position / segment_length;
+            let point = [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+            if in_dash {
+                push_point(&mut current, point);
+                let finished = std::mem::take(&mut current);
+                if finished.len() >= 2 {
+                    dashes.push(finished);
+                }
+                remaining = gap;
+            } else {
+                current.push(point);
+                remaining = dash;
+            }
+            in_dash = !in_dash;
+        }
+        remaining -= segment_length - position;
+        if in_dash {
+            push_point(&mut current, b);
+        }
+    }
+    if current.len() >= 2 {
+        dashes.push(current);
+    }
+    dashes
+}
+
+fn length(v: [f32; 2]) -> f32 {
+    (v[0] * v[0] + v[1] * v[1]).sqrt()
+}
+
+fn subdivision_count(estimate: f32) -> usize {
+    (estimate.ceil() as usize).clamp(1, 256)
+}
+
+fn push_point(points: &mut Vec<[f32; 2]>, point: [f32; 2]) {
+    match points.last() {
+        Some(last) if length([last[0] - point[0], last[1] - point[1]]) < 0.0001 => {}
+        _ => points.push(point)
+    }
+}
+
+fn finish_subpath(result: &mut Vec<FlatSubpath>, current: &mut FlatSubpath) {
+    if current.closed && current.points.len() > 2 {
+        let (first, last) = (current.points[0], current.points[current.points.len() - 1]);
+        if length([last[0] - first[0], last[1] - first[1]]) < 0.0001 {
+            current.points.pop();
+        }
+    }
+    if current.points.len() >= 2 {
+        result.push(FlatSubpath {
+            points: std::mem::take(&mut current.points),
+            closed: current.closed
+        });
+    } else {
+        current.points.clear();
+    }
+    current.closed = false;
+}
+
+fn quad_point(a: [f32; 2], control: [f32; 2], b: [f32; 2], t: f32) -> [f32; 2] {
+    let t_opposite = 1.0 - t;
+    [
+        a[0] * t_opposite * t_opposite + 2.0 * control[0] * t_opposite * t + b[0] * t * t,
+        a[1] * t_opposite * t_opposite + 2.0 * control[1] * t_opposite * t + b[1] * t * t
+    ]
+}
+
+fn cubic_point(a: [f32; 2], control_1: [f32; 2], control_2: [f32; 2], b: [f32; 2], t: f32) -> [f32; 2] {
+    let t_opposite = 1.0 - t;
+    let (k0, k1, k2, k3) = (
+        t_opposite * t_opposite * t_opposite,
+        3.0 * t_opposite * t_opposite * t,
+        3.0 * t_opposite * t * t,
+        t * t * t
+    );
+    [
+        a[0] * k0 + control_1[0] * k1 + control_2[0] * k2 + b[0] * k3,
+        a[1] * k0 + control_1[1] * k1 + control_2[1] * k2 + b[1] * k3
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poly_line_2d::path::{Path, split_dashes};
+
+    #[test]
+    pub fn test_flatten_respects_tolerance() {
+        let mut path = Path::new();
+        path.move_to([100.0, 0.0]).arc_around([0.0, 0.0], std::f32::consts::PI * 2.0).close();
+
+        let coarse = path.flatten(5.0);
+        let fine = path.flatten(0.1);
+        assert_eq!(1, coarse.len());
+        assert!(coarse[0].closed);
+        assert!(fine[0].points.len() > coarse[0].points.len());
+
+        for pair in fine[0].points.windows(2) {
+            let mid = [(pair[0][0] + pair[1][0]) / 2.0, (pair[0][1] + pair[1][1]) / 2.0];
+            let deviation = 100.0 - (mid[0] * mid[0] + mid[1] * mid[1]).sqrt();
+            assert!(deviation <= 0.11, "deviation {} is over tolerance", deviation);
+        }
+    }
+
+    #[test]
+    pub fn test_subpaths() {
+        let mut path = Path::new();
+        path.move_to([0.0, 0.0])
+            .quad_to([50.0, 100.0], [100.0, 0.0])
+            .cubic_to([120.0, -50.0], [150.0, 50.0], [200.0, 0.0])
+            .move_to([0.0, 200.0])
+            .line_to([100.0, 200.0])
+            .line_to([100.0, 300.0])
+            .close();
+
+        let subpaths = path.flatten(0.5);
+        assert_eq!(2, subpaths.len());
+        assert!(!subpaths[0].closed);
+        assert_eq!([0.0, 0.0], subpaths[0].points[0]);
+        assert_eq!([200.0, 0.0], *subpaths[0].points.last().unwrap());
+        assert!(subpaths[1].closed);
+        assert_eq!(3, subpaths[1].points.len());
+    }
+
+    #[test]
+    pub fn test_split_dashes() {
+        let dashes = split_dashes(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]], false, 4.0, 2.0, 0.0);
+        assert_eq!(
+            vec![
+                vec![[0.0, 0.0], [4.0, 0.0]],
+                vec![[6.0, 0.0], [10.0, 0.0]],
+                vec![[10.0, 2.0], [10.0, 6.0]],
+                vec![[10.0, 8.0], [10.0, 10.0]]
+            ],
+            dashes
+        );
+
+        let shifted = split_dashes(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]], false, 4.0, 2.0, 3.0);
+        assert_eq!(vec![[0.0, 0.0], [1.0, 0.0]], shifted[0]);
+        assert_eq!(vec![[3.0, 0.0], [7.0, 0.0]], shifted[1]);
+        assert_eq!(vec![[9.0, 0.0], [10.0, 0.0], [10.0, 3.0]], shifted[2]);
+
+        let square = split_dashes(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]], true, 10.0, 0.0, 0.0);
+        assert_eq!(1, square.len());
+        assert_eq!(5, square[0].len());
+    }
+}
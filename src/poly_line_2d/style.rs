@@ -7,10 +7,28 @@ pub enum JointStyle {
 #[derive(Copy, Clone)]
 pub enum EndCapStyle {
     Butt,
-    Square
+    Square,
+    /// Half-disc of the line thickness around the end point
+    Round
 }
 #[derive(Copy, Clone)]
 pub enum LineStripStyle {
     Open,
     Closed
 }
+
+#[derive(Copy, Clone)]
+pub enum ThicknessSpace {
+    /// Thickness is measured in the same units as the submitted points
+    World,
+    /// Thickness is measured in screen pixels regardless of the current transform
+    Screen
+}
+
+#[derive(Copy, Clone)]
+pub enum DashStyle {
+    Solid,
+    /// Lengths are measured in the same space as the line thickness,
+    /// `offset` is how far into the pattern the line starts
+    Dashed { dash: f32, gap: f32, offset: f32 }
+}
@@ -0,0 +1,124 @@
+use std::ops::Mul;
+
+/// 2D affine transform stored as the first two rows of a 3x3 matrix:
+/// | a c tx |
+/// | b d ty |
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform2D {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub tx: f32,
+    pub ty: f32
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform2D {
+    pub fn identity() -> Self {
+        Self { a: 1.0, b: 0.0, c: 0.0, d: 1.0, tx: 0.0, ty: 0.0 }
+    }
+
+    pub fn translation(x: f32, y: f32) -> Self {
+        Self { tx: x, ty: y, ..Self::identity() }
+    }
+
+    pub fn scaling(sx: f32, sy: f32) -> Self {
+        Self { a: sx, d: sy, ..Self::identity() }
+    }
+
+    pub fn rotation(radians: f32) -> Self {
+        let (sn, cs) = radians.sin_cos();
+        Self { a: cs, b: sn, c: -sn, d: cs, tx: 0.0, ty: 0.0 }
+    }
+
+    pub fn apply(&self, point: [f32; 2]) -> [f32; 2] {
+        [
+            self.a * point[0] + self.c * point[1] + self.tx,
+            self.b * point[0] + self.d * point[1] + self.ty
+        ]
+    }
+
+    pub fn apply_vector(&self, vector: [f32; 2]) -> [f32; 2] {
+        [
+            self.a * vector[0] + self.c * vector[1],
+            self.b * vector[0] + self.d * vector[1]
+        ]
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// Average linear scale of the transform, used to map lengths such as stroke thickness
+    pub fn scale_factor(&self) -> f32 {
+        self.determinant().abs().sqrt()
+    }
+
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Self {
+            a,
+            b,
+            c,
+            d,
+            tx: -(a * self.tx + c * self.ty),
+            ty: -(b * self.tx + d * self.ty)
+        })
+    }
+}
+
+impl Mul for Transform2D {
+    type Output = Transform2D;
+
+    /// `lhs * rhs` applies `rhs` first and `lhs` second
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::Output {
+            a: self.a * rhs.a + self.c * rhs.b,
+            b: self.b * rhs.a + self.d * rhs.b,
+            c: self.a * rhs.c + self.c * rhs.d,
+            d: self.b * rhs.c + self.d * rhs.d,
+            tx: self.a * rhs.tx + self.c * rhs.ty + self.tx,
+            ty: self.b * rhs.tx + self.d * rhs.ty + self.ty
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::poly_line_2d::transform::Transform2D;
+
+    fn assert_close(lhs: [f32; 2], rhs: [f32; 2]) {
+        assert!((lhs[0] - rhs[0]).abs() < 0.0001, "{:?} != {:?}", lhs, rhs);
+        assert!((lhs[1] - rhs[1]).abs() < 0.0001, "{:?} != {:?}", lhs, rhs);
+    }
+
+    #[test]
+    pub fn test_composition_order() {
+        let transform = Transform2D::scaling(2.0, 2.0) * Transform2D::translation(10.0, -5.0);
+        assert_close(transform.apply([1.0, 1.0]), [22.0, -8.0]);
+
+        let transform = Transform2D::translation(10.0, -5.0) * Transform2D::rotation(90f32.to_radians());
+        assert_close(transform.apply([1.0, 0.0]), [10.0, -4.0]);
+        assert!((transform.scale_factor() - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    pub fn test_inverse() {
+        let transform = Transform2D::translation(3.0, 4.0)
+            * Transform2D::rotation(0.3)
+            * Transform2D::scaling(2.0, 0.5);
+        let inverse = transform.inverse().unwrap();
+        assert_close(inverse.apply(transform.apply([7.0, -2.0])), [7.0, -2.0]);
+        assert!(Transform2D::scaling(0.0, 1.0).inverse().is_none());
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+use crate::terrain::{WeightGrid, CellRect};
+use crate::terrain::contour::{Contour, ContourPipeline};
+use crate::terrain::contour_cache::ContourCache;
+
+/// Ordered edge list of one contour with solid on the left of every edge.
+/// Open chains end on a chunk border, their ghost vertices are the neighbouring vertices
+/// of the chain continuing in the next chunk, so physics can resolve contacts across the seam
+#[derive(Clone, Debug)]
+pub struct ChainShape {
+    pub vertices: Vec<[f32; 2]>,
+    pub closed: bool,
+    pub prev_ghost: Option<[f32; 2]>,
+    pub next_ghost: Option<[f32; 2]>
+}
+
+impl ChainShape {
+    pub fn from_contour(contour: &Contour) -> Self {
+        Self {
+            vertices: contour.points.clone(),
+            closed: contour.closed,
+            prev_ghost: None,
+            next_ghost: None
+        }
+    }
+
+    /// Chain without ghost vertices yet
+    pub fn open(vertices: Vec<[f32; 2]>) -> Self {
+        Self { vertices, closed: false, prev_ghost: None, next_ghost: None }
+    }
+
+    /// Edges as pairs of vertices, including the closing one of a loop
+    pub fn edges(&self) -> impl Iterator<Item = ([f32; 2], [f32; 2])> + '_ {
+        let count = self.vertices.len();
+        let edge_count = match (self.closed, count) {
+            (_, 0) | (_, 1) => 0,
+            (true, _) => count,
+            (false, _) => count - 1
+        };
+        (0..edge_count).map(move |id| (self.vertices[id], self.vertices[(id + 1) % count]))
+    }
+}
+
+/// Chains of a single chunk together with the world-space bounds of the chunk
+#[derive(Clone, Debug)]
+pub struct ChunkChains {
+    pub rect: CellRect,
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    pub chains: Vec<ChainShape>
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct RayHit {
+    pub point: [f32; 2],
+    /// Surface normal pointing out of the solid
+    pub normal: [f32; 2],
+    pub distance: f32
+}
+
+/// Terrain boundary split into per-chunk chain shapes, built by the same pipeline the contours are drawn with.
+/// Contours are joined and processed across chunks before they're split, so chains continue smoothly over the seams
+pub struct TerrainCollision {
+    chunk_size: usize,
+    contours: ContourCache,
+    chunks: Vec<ChunkChains>,
+    /// Whether every vertex is solid, which decides `contains` when there is no boundary to measure against
+    solid_everywhere: bool
+}
+
+impl TerrainCollision {
+    pub fn build(grid: &WeightGrid, pipeline: &ContourPipeline, chunk_size: usize) -> Self {
+        let chunk_size = chunk_size.max(1);
+        let mut collision = Self {
+            chunk_size,
+            contours: ContourCache::new(grid, chunk_size, *pipeline),
+            chunks: Vec::new(),
+            solid_everywhere: false
+        };
+        collision.chunks = grid.cell_rect()
+            .chunks(chunk_size)
+            .map(|rect| ChunkChains {
+                rect,
+                min: grid.vertex_position(rect.x, rect.y),
+                max: grid.vertex_position(rect.x + rect.width, rect.y + rect.height),
+                chains: Vec::new()
+            })
+            .collect();
+        collision.rebuild(grid, pipeline, grid.cell_rect());
+        collision
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn chunks(&self) -> &[ChunkChains] {
+        &self.chunks
+    }
+
+    /// Retraces the chunks overlapping `dirty` and rebuilds the chains of the contours passing through them.
+    /// Returns how many chunks got new chains, the chains of the other chunks are left as they are
+    pub fn rebuild(&mut self, grid: &WeightGrid, pipeline: &ContourPipeline, dirty: CellRect) -> usize {
+        self.contours.set_pipeline(*pipeline);
+        self.contours.mark_dirty(dirty);
+        self.contours.update(grid, grid.cell_rect());
+        let mut rebuilt = vec![false; self.chunks.len()];
+        for &id in self.contours.reprocessed_chunks() {
+            rebuilt[id] = true;
+        }
+        self.split_contours(grid, &rebuilt);
+        self.link_ghosts(&rebuilt);
+        // without any boundary all vertices lie on the same side of the iso level
+        self.solid_everywhere = self.edges().next().is_none()
+            && grid.weights().iter().all(|&it| it >= grid.iso_level());
+        self.contours.reprocessed_chunks().len()
+    }
+
+    /// Cuts the contours through the `rebuilt` chunks where their edges move to another chunk.
+    /// Edges belong to the chunk holding their midpoint, or to the closest chunk the contour was traced in,
+    /// so the chains of a contour never end up in chunks which are not rebuilt with it.
+    /// Chunk bounds grow to hold their chains since processed edges may stick out of the chunk a little
+    fn split_contours(&mut self, grid: &WeightGrid, rebuilt: &[bool]) {
+        let chunk_extent = grid.cell_size() * self.chunk_size as f32;
+        let chunks_x = grid.cell_rect().width.div_ceil(self.chunk_size);
+        let chunks_y = grid.cell_rect().height.div_ceil(self.chunk_size);
+        let to_chunk = |coord: f32, count: usize| ((coord / chunk_extent).max(0.0) as usize).min(count - 1);
+        let rects = self.chunks.iter().map(|it| it.rect).collect::<Vec<_>>();
+        let distance_to_chunk = |point: [f32; 2], id: usize| {
+            let rect = rects[id];
+            let (min, max) = (grid.vertex_position(rect.x, rect.y), grid.vertex_position(rect.x + rect.width, rect.y + rect.height));
+            let dx = (min[0] - point[0]).max(point[0] - max[0]).max(0.0);
+            let dy = (min[1] - point[1]).max(point[1] - max[1]).max(0.0);
+            dx * dx + dy * dy
+        };
+
+        for chunk in self.chunks.iter_mut().zip(rebuilt).filter(|(_, rebuilt)| **rebuilt).map(|(chunk, _)| chunk) {
+            chunk.chains.clear();
+            chunk.min = grid.vertex_position(chunk.rect.x, chunk.rect.y);
+            chunk.max = grid.vertex_position(chunk.rect.x + chunk.rect.width, chunk.rect.y + chunk.rect.height);
+        }
+        for (contour, traced_in) in self.contours.contours_with_chunks() {
+            let points = &contour.points;
+            let count = points.len();
+            if count < 2 || !traced_in.iter().any(|id| rebuilt[*id]) {
+                continue;
+            }
+            let edge_count = if contour.closed { count } else { count - 1 };
+            let edge_chunk = |id: usize| {
+                let (a, b) = (points[id], points[(id + 1) % count]);
+                let midpoint = [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+                let chunk = to_chunk(midpoint[1], chunks_y) * chunks_x + to_chunk(midpoint[0], chunks_x);
+                if traced_in.contains(&chunk) && rebuilt[chunk] {
+                    return chunk;
+                }
+                traced_in
+                    .iter()
+                    .copied()
+                    .filter(|id| rebuilt[*id])
+                    .min_by(|&a, &b| distance_to_chunk(midpoint, a).total_cmp(&distance_to_chunk(midpoint, b)))
+                    .unwrap()
+            };
+
+            // a loop leaving its chunk is cut where an edge enters another chunk, so no chain wraps around
+            let first = match contour.closed {
+                true => (0..edge_count).find(|&id| edge_chunk(id) != edge_chunk((id + edge_count - 1) % edge_count)),
+                false => Some(0)
+            };
+            let Some(first) = first else {
+                self.chunks[edge_chunk(0)].chains.push(ChainShape::from_contour(contour));
+                continue;
+            };
+            let mut chunk = edge_chunk(first);
+            let mut vertices = vec![points[first]];
+            for step in 0..edge_count {
+                let id = (first + step) % count;
+                if edge_chunk(id) != chunk {
+                    let vertices = std::mem::replace(&mut vertices, vec![points[id]]);
+                    self.chunks[chunk].chains.push(ChainShape::open(vertices));
+                    chunk = edge_chunk(id);
+                }
+                vertices.push(points[(id + 1) % count]);
+            }
+            self.chunks[chunk].chains.push(ChainShape::open(vertices));
+        }
+
+        for chunk in self.chunks.iter_mut().zip(rebuilt).filter(|(_, rebuilt)| **rebuilt).map(|(chunk, _)| chunk) {
+            for vertex in chunk.chains.iter().flat_map(|it| it.vertices.iter()) {
+                chunk.min = [chunk.min[0].min(vertex[0]), chunk.min[1].min(vertex[1])];
+                chunk.max = [chunk.max[0].max(vertex[0]), chunk.max[1].max(vertex[1])];
+            }
+        }
+    }
+
+    /// Open chains meet at exactly the same crossing point on a chunk border. Chains of a contour
+    /// are rebuilt together, so only the chains of the `rebuilt` chunks need their ghosts again
+    fn link_ghosts(&mut self, rebuilt: &[bool]) {
+        let key = |point: [f32; 2]| (point[0].to_bits(), point[1].to_bits());
+        let mut starts = HashMap::new();
+        let mut ends = HashMap::new();
+        for chunk in self.chunks.iter().zip(rebuilt).filter(|(_, rebuilt)| **rebuilt).map(|(chunk, _)| chunk) {
+            for chain in chunk.chains.iter().filter(|it| !it.closed && it.vertices.len() >= 2) {
+                starts.insert(key(chain.vertices[0]), chain.vertices[1]);
+                ends.insert(key(chain.vertices[chain.vertices.len() - 1]), chain.vertices[chain.vertices.len() - 2]);
+            }
+        }
+        for chunk in self.chunks.iter_mut().zip(rebuilt).filter(|(_, rebuilt)| **rebuilt).map(|(chunk, _)| chunk) {
+            for chain in chunk.chains.iter_mut().filter(|it| !it.closed && it.vertices.len() >= 2) {
+                chain.prev_ghost = ends.get(&key(chain.vertices[0])).copied();
+                chain.next_ghost = starts.get(&key(chain.vertices[chain.vertices.len() - 1])).copied();
+            }
+        }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = ([f32; 2], [f32; 2])> + '_ {
+        self.chunks.iter().flat_map(|chunk| chunk.chains.iter().flat_map(|chain| chain.edges()))
+    }
+
+    /// Closest boundary crossing along the ray from `origin` in direction `dir`, up to `max_distance`.
+    /// Edges are hit from both sides, the normal always points out of the solid
+    pub fn raycast(&self, origin: [f32; 2], dir: [f32; 2], max_distance: f32) -> Option<RayHit> {
+        let length = (dir[0] * dir[0] + dir[1] * dir[1]).sqrt();
+        if length < f32::EPSILON {
+            return None;
+        }
+        let dir = [dir[0] / length, dir[1] / length];
+        let mut closest: Option<RayHit> = None;
+        for chunk in self.chunks.iter() {
+            let limit = closest.map_or(max_distance, |it| it.distance);
+            if !ray_hits_box(origin, dir, limit, chunk.min, chunk.max) {
+                continue;
+            }
+            for (a, b) in chunk.chains.iter().flat_map(|chain| chain.edges()) {
+                let limit = closest.map_or(max_distance, |it| it.distance);
+                if let Some(distance) = ray_segment_distance(origin, dir, a, b) {
+                    if distance <= limit {
+                        closest = Some(RayHit {
+                            point: [origin[0] + dir[0] * distance, origin[1] + dir[1] * distance],
+                            normal: outward_normal(a, b),
+                            distance
+                        });
+                    }
+                }
+            }
+        }
+        closest
+    }
+
+    /// Whether `point` lies in the solid. Decided by the side of the closest boundary edge,
+    /// or by the grid itself when it has no boundary at all, e.g. on an entirely filled map
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        let mut min_distance = f32::MAX;
+        let mut closest_edges = Vec::new();
+        for (a, b) in self.edges() {
+            let closest_point = closest_point_on_segment(point, a, b);
+            let distance = distance_squared(point, closest_point).sqrt();
+            if distance < min_distance - CLOSEST_EPSILON {
+                min_distance = distance;
+                closest_edges.clear();
+            }
+            if distance <= min_distance + CLOSEST_EPSILON {
+                closest_edges.push((closest_point, outward_normal(a, b)));
+            }
+        }
+        let Some(&(closest_point, _)) = closest_edges.first() else {
+            return self.solid_everywhere;
+        };
+        // at a shared vertex the summed normals tell convex corners from concave ones
+        let normal = closest_edges
+            .iter()
+            .fold([0.0, 0.0], |sum, (_, normal)| [sum[0] + normal[0], sum[1] + normal[1]]);
+        let offset = [point[0] - closest_point[0], point[1] - closest_point[1]];
+        offset[0] * normal[0] + offset[1] * normal[1] < 0.0
+    }
+}
+
+const CLOSEST_EPSILON: f32 = 0.0001;
+
+/// Unit normal on the right of the edge, where the empty side is
+pub fn outward_normal(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    let dir = [b[0] - a[0], b[1] - a[1]];
+    let length = (dir[0] * dir[0] + dir[1] * dir[1]).sqrt().max(f32::EPSILON);
+    [dir[1] / length, -dir[0] / length]
+}
+
+fn distance_squared(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - b[0]) * (a[0] - b[0]) + (a[1] - b[1]) * (a[1] - b[1])
+}
+
+pub fn closest_point_on_segment(point: [f32; 2], a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let length_squared = ab[0] * ab[0] + ab[1] * ab[1];
+    if length_squared < f32::EPSILON {
+        return a;
+    }
+    let t = (((point[0] - a[0]) * ab[0] + (point[1] - a[1]) * ab[1]) / length_squared).clamp(0.0, 1.0);
+    [a[0] + ab[0] * t, a[1] + ab[1] * t]
+}
+
+/// Distance along the normalized ray to the segment, if they cross
+pub fn ray_segment_distance(origin: [f32; 2], dir: [f32; 2], a: [f32; 2], b: [f32; 2]) -> Option<f32> {
+    let edge = [b[0] - a[0], b[1] - a[1]];
+    let denominator = dir[0] * edge[1] - dir[1] * edge[0];
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let to_a = [a[0] - origin[0], a[1] - origin[1]];
+    let distance = (to_a[0] * edge[1] - to_a[1] * edge[0]) / denominator;
+    let t = (to_a[0] * dir[1] - to_a[1] * dir[0]) / denominator;
+    if distance >= 0.0 && (0.0..=1.0).contains(&t) {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+/// Slab test of the ray segment against an axis-aligned box
+fn ray_hits_box(origin: [f32; 2], dir: [f32; 2], max_distance: f32, min: [f32; 2], max: [f32; 2]) -> bool {
+    let (mut near, mut far) = (0.0f32, max_distance);
+    for axis in 0..2 {
+        if dir[axis].abs() < f32::EPSILON {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return false;
+            }
+            continue;
+        }
+        let t0 = (min[axis] - origin[axis]) / dir[axis];
+        let t1 = (max[axis] - origin[axis]) / dir[axis];
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+        if near > far {
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::{WeightGrid, CellRect};
+    use crate::terrain::contour::ContourPipeline;
+    use crate::terrain::collision::{TerrainCollision, ChunkChains};
+    use crate::terrain::shape::Shape;
+
+    /// Solid block over vertices 3..=12 x 3..=6, crossing the chunk border at x = 8
+    fn block() -> TerrainCollision {
+        let mut grid = WeightGrid::new(17, 11, 1.0, 0.001);
+        for y in 3..=6 {
+            for x in 3..=12 {
+                grid.set(x, y, 0.5);
+            }
+        }
+        TerrainCollision::build(&grid, &ContourPipeline::default(), 8)
+    }
+
+    #[test]
+    pub fn test_chains_are_linked_across_chunks() {
+        let collision = block();
+        let open = collision.chunks()
+            .iter()
+            .flat_map(|chunk| chunk.chains.iter())
+            .filter(|chain| !chain.closed)
+            .collect::<Vec<_>>();
+        assert_eq!(2, open.len());
+        for chain in open.iter() {
+            assert!(chain.prev_ghost.is_some() && chain.next_ghost.is_some());
+            let last = chain.vertices[chain.vertices.len() - 1];
+            let next = open.iter().find(|other| other.vertices[0] == last).unwrap();
+            assert_eq!(Some(next.vertices[1]), chain.next_ghost);
+        }
+    }
+
+    #[test]
+    pub fn test_raycast_and_contains() {
+        let collision = block();
+        let hit = collision.raycast([0.0, 5.0], [1.0, 0.0], 100.0).unwrap();
+        assert!((hit.point[0] - 2.5).abs() < 0.0001 && (hit.point[1] - 5.0).abs() < 0.0001);
+        assert!((hit.distance - 2.5).abs() < 0.0001);
+        assert!((hit.normal[0] + 1.0).abs() < 0.0001 && hit.normal[1].abs() < 0.0001);
+
+        let from_below = collision.raycast([10.0, 10.0], [0.0, -3.0], 100.0).unwrap();
+        assert!((from_below.point[1] - 6.5).abs() < 0.0001);
+        assert!((from_below.normal[1] - 1.0).abs() < 0.0001);
+
+        assert!(collision.raycast([0.0, 5.0], [1.0, 0.0], 2.0).is_none());
+        assert!(collision.raycast([0.0, 1.0], [1.0, 0.0], 100.0).is_none());
+
+        assert!(collision.contains([8.0, 5.0]));
+        assert!(collision.contains([12.4, 3.0]));
+        assert!(!collision.contains([12.6, 5.0]));
+        assert!(!collision.contains([2.0, 2.0]));
+        assert!(!collision.contains([8.0, 9.0]));
+    }
+
+    #[test]
+    pub fn test_rebuild_keeps_chains_of_untouched_chunks() {
+        let mut grid = WeightGrid::new(33, 33, 1.0, 0.001);
+        for y in 2..=5 {
+            for x in 2..=5 {
+                grid.set(x, y, 0.5);
+            }
+        }
+        // the second block crosses the chunk border at x = 24
+        for y in 18..=21 {
+            for x in 20..=28 {
+                grid.set(x, y, 0.5);
+            }
+        }
+        let pipeline = ContourPipeline::default();
+        let mut collision = TerrainCollision::build(&grid, &pipeline, 8);
+        let before = collision.chunks().to_vec();
+
+        let dirty = grid.carve(&Shape::Circle { center: [26.0, 20.0], radius: 1.2 });
+        assert_eq!(2, collision.rebuild(&grid, &pipeline, dirty));
+        for (id, (old, new)) in before.iter().zip(collision.chunks()).enumerate() {
+            if id == 2 * 4 + 2 || id == 2 * 4 + 3 {
+                continue;
+            }
+            assert_eq!(old.chains.len(), new.chains.len());
+            for (old, new) in old.chains.iter().zip(new.chains.iter()) {
+                assert_eq!(old.vertices, new.vertices);
+            }
+        }
+
+        let fresh = TerrainCollision::build(&grid, &pipeline, 8);
+        for (rebuilt, fresh) in collision.chunks().iter().zip(fresh.chunks()) {
+            let edges = |chunk: &ChunkChains| {
+                chunk.chains.iter().map(|it| it.edges().count()).sum::<usize>()
+            };
+            assert_eq!(edges(fresh), edges(rebuilt));
+        }
+        let open = collision.chunks().iter().flat_map(|it| it.chains.iter()).filter(|it| !it.closed);
+        assert!(open.clone().count() >= 2);
+        assert!(open.into_iter().all(|it| it.prev_ghost.is_some() && it.next_ghost.is_some()));
+        assert_eq!(0, collision.rebuild(&grid, &pipeline, CellRect::new(0, 0, 0, 0)));
+    }
+
+    #[test]
+    pub fn test_contains_without_boundary() {
+        let mut grid = WeightGrid::new(17, 17, 1.0, 0.001);
+        let pipeline = ContourPipeline::default();
+        assert!(!TerrainCollision::build(&grid, &pipeline, 8).contains([8.0, 8.0]));
+
+        grid.weights_mut().iter_mut().for_each(|it| *it = 1.0);
+        let mut collision = TerrainCollision::build(&grid, &pipeline, 8);
+        assert!(collision.edges().next().is_none());
+        assert!(collision.contains([8.0, 8.0]) && collision.contains([0.5, 16.0]));
+
+        let dirty = grid.carve(&Shape::Circle { center: [8.0, 8.0], radius: 1.5 });
+        collision.rebuild(&grid, &pipeline, dirty);
+        assert!(!collision.contains([8.0, 8.0]) && collision.contains([3.0, 3.0]));
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use crate::terrain::{WeightGrid, CellRect};
+use crate::terrain::smoothing::{Smoothing, smooth_contour};
+use crate::terrain::simplify::{Simplification, simplify_contour};
+
+/// Traced iso-line. Solid lies on the left of the travel direction,
+/// i.e. on the side of `LineSegment::normal` (`[-dir.y, dir.x]`).
+/// Open contours start and end on the border of the traced rectangle
+#[derive(Clone, Debug)]
+pub struct Contour {
+    pub points: Vec<[f32; 2]>,
+    pub closed: bool
+}
+
+/// Grid edge identified by its first vertex; horizontal edges go to `(x + 1, y)`, vertical ones to `(x, y + 1)`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EdgeKey {
+    pub x: usize,
+    pub y: usize,
+    pub horizontal: bool
+}
+
+/// Oriented piece of the iso-line inside a single cell
+#[derive(Copy, Clone, Debug)]
+pub struct CellSegment {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+    pub start_edge: EdgeKey,
+    pub end_edge: EdgeKey
+}
+
+struct EdgeCrossing {
+    point: [f32; 2],
+    edge: EdgeKey,
+    solid_corner: [f32; 2]
+}
+
+fn edge_crossing(grid: &WeightGrid, edge: EdgeKey) -> Option<EdgeCrossing> {
+    let (x1, y1) = if edge.horizontal { (edge.x + 1, edge.y) } else { (edge.x, edge.y + 1) };
+    let (solid_0, solid_1) = (grid.is_solid(edge.x, edge.y), grid.is_solid(x1, y1));
+    if solid_0 == solid_1 {
+        return None;
+    }
+    let (p0, p1) = (grid.vertex_position(edge.x, edge.y), grid.vertex_position(x1, y1));
+    let (solid, empty, extent) = if solid_0 {
+        (p0, p1, grid.get(edge.x, edge.y))
+    } else {
+        (p1, p0, grid.get(x1, y1))
+    };
+    let extent = extent.clamp(0.0, 1.0);
+    Some(EdgeCrossing {
+        point: [
+            solid[0] + (empty[0] - solid[0]) * extent,
+            solid[1] + (empty[1] - solid[1]) * extent
+        ],
+        edge,
+        solid_corner: solid
+    })
+}
+
+fn cross(lhs: [f32; 2], rhs: [f32; 2]) -> f32 {
+    lhs[0] * rhs[1] - lhs[1] * rhs[0]
+}
+
+fn oriented_segment(p: &EdgeCrossing, q: &EdgeCrossing) -> CellSegment {
+    let pq = [q.point[0] - p.point[0], q.point[1] - p.point[1]];
+    let score = cross(pq, [p.solid_corner[0] - p.point[0], p.solid_corner[1] - p.point[1]])
+        + cross(pq, [q.solid_corner[0] - q.point[0], q.solid_corner[1] - q.point[1]]);
+    if score >= 0.0 {
+        CellSegment { start: p.point, end: q.point, start_edge: p.edge, end_edge: q.edge }
+    } else {
+        CellSegment { start: q.point, end: p.point, start_edge: q.edge, end_edge: p.edge }
+    }
+}
+
+/// Iso-line segments of cell `(x, y)`, at most two of them for saddle cells
+pub fn cell_segments(grid: &WeightGrid, x: usize, y: usize) -> [Option<CellSegment>; 2] {
+    let top = edge_crossing(grid, EdgeKey { x, y, horizontal: true });
+    let bottom = edge_crossing(grid, EdgeKey { x, y: y + 1, horizontal: true });
+    let left = edge_crossing(grid, EdgeKey { x, y, horizontal: false });
+    let right = edge_crossing(grid, EdgeKey { x: x + 1, y, horizontal: false });
+
+    match (left, right, top, bottom) {
+        (Some(l), Some(r), None, None) => [Some(oriented_segment(&l, &r)), None],
+        (None, None, Some(t), Some(b)) => [Some(oriented_segment(&t, &b)), None],
+        (Some(l), None, Some(t), None) => [Some(oriented_segment(&l, &t)), None],
+        (None, Some(r), Some(t), None) => [Some(oriented_segment(&r, &t)), None],
+        (Some(l), None, None, Some(b)) => [Some(oriented_segment(&l, &b)), None],
+        (None, Some(r), None, Some(b)) => [Some(oriented_segment(&r, &b)), None],
+        (Some(l), Some(r), Some(t), Some(b)) if grid.is_solid(x, y) => {
+            // north-west and south-east corners are solid, the center is empty
+            [Some(oriented_segment(&r, &b)), Some(oriented_segment(&l, &t))]
+        }
+        (Some(l), Some(r), Some(t), Some(b)) => {
+            [Some(oriented_segment(&r, &t)), Some(oriented_segment(&l, &b))]
+        }
+        _ => [None, None]
+    }
+}
+
+/// Traces the iso-line of `grid` inside `rect` and stitches cell segments into polylines
+pub fn trace_contours(grid: &WeightGrid, rect: CellRect) -> Vec<Contour> {
+    let rect = rect.intersection(&grid.cell_rect());
+    let mut segments: Vec<CellSegment> = Vec::new();
+    for y in rect.y..rect.y + rect.height {
+        for x in rect.x..rect.x + rect.width {
+            segments.extend(cell_segments(grid, x, y).iter().flatten().copied());
+        }
+    }
+
+    let starts = segments
+        .iter()
+        .enumerate()
+        .map(|(id, it)| (it.start_edge, id))
+        .collect::<HashMap<_, _>>();
+    let next = segments
+        .iter()
+        .map(|it| starts.get(&it.end_edge).copied())
+        .collect::<Vec<_>>();
+    let mut has_prev = vec![false; segments.len()];
+    for id in next.iter().flatten() {
+        has_prev[*id] = true;
+    }
+
+    let mut visited = vec![false; segments.len()];
+    let mut contours = Vec::new();
+
+    for first in 0..segments.len() {
+        if has_prev[first] {
+            continue;
+        }
+        let mut points = vec![segments[first].start];
+        let mut current = Some(first);
+        while let Some(id) = current {
+            visited[id] = true;
+            points.push(segments[id].end);
+            current = next[id];
+        }
+        contours.push(Contour { points, closed: false });
+    }
+
+    for first in 0..segments.len() {
+        if visited[first] {
+            continue;
+        }
+        let mut points = Vec::new();
+        let mut current = Some(first);
+        while let Some(id) = current {
+            if visited[id] {
+                break;
+            }
+            visited[id] = true;
+            points.push(segments[id].start);
+            current = next[id];
+        }
+        contours.push(Contour { points, closed: true });
+    }
+
+    contours
+}
+
+/// Stitches open contours whose last point is the first point of another one, e.g. the pieces traced per chunk.
+/// Pieces of the same iso-line meet at exactly the same edge crossing, chains coming back to their start are closed
+pub fn join_contours(contours: Vec<Contour>) -> Vec<Contour> {
+    let key = |point: [f32; 2]| (point[0].to_bits(), point[1].to_bits());
+    let (mut joined, open): (Vec<_>, Vec<_>) = contours
+        .into_iter()
+        .partition(|it| it.closed || it.points.len() < 2);
+
+    // crossings at a fully weighted vertex coincide, so several pieces can start at the same point.
+    // The first one continues the chain, the others start chains of their own
+    let mut starts = HashMap::new();
+    for (id, it) in open.iter().enumerate() {
+        starts.entry(key(it.points[0])).or_insert(id);
+    }
+    let next = open
+        .iter()
+        .map(|it| starts.get(&key(it.points[it.points.len() - 1])).copied())
+        .collect::<Vec<_>>();
+    let mut has_prev = vec![false; open.len()];
+    for id in next.iter().flatten() {
+        has_prev[*id] = true;
+    }
+
+    let mut visited = vec![false; open.len()];
+    for first in 0..open.len() {
+        if has_prev[first] {
+            continue;
+        }
+        let mut points = open[first].points.clone();
+        visited[first] = true;
+        let mut current = next[first];
+        while let Some(id) = current {
+            if visited[id] {
+                break;
+            }
+            visited[id] = true;
+            points.extend_from_slice(&open[id].points[1..]);
+            current = next[id];
+        }
+        joined.push(Contour { points, closed: false });
+    }
+
+    for first in 0..open.len() {
+        if visited[first] {
+            continue;
+        }
+        let mut points = Vec::new();
+        let mut current = Some(first);
+        while let Some(id) = current {
+            if visited[id] {
+                break;
+            }
+            visited[id] = true;
+            // the last point is the first one of the next piece
+            points.extend_from_slice(&open[id].points[..open[id].points.len() - 1]);
+            current = next[id];
+        }
+        joined.push(Contour { points, closed: true });
+    }
+
+    joined
+}
+
+/// Post-processing applied to traced contours: simplification first, then smoothing
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ContourPipeline {
+    pub simplification: Simplification,
+    pub smoothing: Smoothing
+}
+
+impl Default for ContourPipeline {
+    fn default() -> Self {
+        Self {
+            simplification: Simplification::None,
+            smoothing: Smoothing::None
+        }
+    }
+}
+
+impl ContourPipeline {
+    /// Simplifies and smooths whole contours. Pieces traced per chunk have to be joined first,
+    /// otherwise every chunk border gets its own end tangents and loops crossing it stay open
+    pub fn process(&self, contours: &[Contour]) -> Vec<Contour> {
+        contours
+            .iter()
+            .map(|it| simplify_contour(it, self.simplification))
+            .map(|it| smooth_contour(&it, self.smoothing))
+            .collect()
+    }
+
+    /// Traces and processes the iso-line inside `rect`
+    pub fn run(&self, grid: &WeightGrid, rect: CellRect) -> Vec<Contour> {
+        self.process(&trace_contours(grid, rect))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::{WeightGrid, CellRect};
+    use crate::terrain::contour::{trace_contours, join_contours, ContourPipeline};
+    use crate::terrain::smoothing::Smoothing;
+    use crate::terrain::simplify::Simplification;
+    use crate::terrain::measure::signed_area;
+
+    #[test]
+    pub fn test_single_vertex_island() {
+        let mut grid = WeightGrid::new(5, 5, 10.0, 0.001);
+        grid.set(2, 2, 0.5);
+
+        let contours = trace_contours(&grid, grid.cell_rect());
+        assert_eq!(1, contours.len());
+        assert!(contours[0].closed);
+        assert_eq!(4, contours[0].points.len());
+        // diamond with half-diagonals of 5 units, solid on the left means positive area
+        assert!((signed_area(&contours[0].points) - 50.0).abs() < 0.001);
+    }
+
+    #[test]
+    pub fn test_hole_is_wound_backwards() {
+        let mut grid = WeightGrid::new(5, 5, 1.0, 0.001);
+        grid.weights_mut().iter_mut().for_each(|it| *it = 0.5);
+        grid.set(2, 2, 0.0);
+
+        let contours = trace_contours(&grid, CellRect::new(1, 1, 2, 2));
+        assert_eq!(1, contours.len());
+        assert!(contours[0].closed);
+        assert!(signed_area(&contours[0].points) < 0.0);
+    }
+
+    #[test]
+    pub fn test_contours_open_on_rect_border() {
+        let mut grid = WeightGrid::new(9, 9, 1.0, 0.001);
+        for y in 2..7 {
+            for x in 2..7 {
+                grid.set(x, y, 1.0);
+            }
+        }
+
+        let whole = trace_contours(&grid, grid.cell_rect());
+        assert_eq!(1, whole.len());
+        assert!(whole[0].closed);
+
+        let left_half = trace_contours(&grid, CellRect::new(0, 0, 4, 8));
+        assert_eq!(1, left_half.len());
+        assert!(!left_half[0].closed);
+        assert!((left_half[0].points[0][0] - 4.0).abs() < 0.001);
+        assert!((left_half[0].points.last().unwrap()[0] - 4.0).abs() < 0.001);
+    }
+
+    #[test]
+    pub fn test_joined_chunk_pieces_process_like_one_loop() {
+        let mut grid = WeightGrid::new(17, 17, 1.0, 0.001);
+        for y in 3..13 {
+            for x in 2..12 {
+                let (dx, dy) = (x as f32 - 7.3, y as f32 - 7.4);
+                grid.set(x, y, (1.0 - (dx * dx + dy * dy).sqrt() / 5.0).max(0.0));
+            }
+        }
+        let pieces = grid.cell_rect()
+            .chunks(8)
+            .flat_map(|rect| trace_contours(&grid, rect))
+            .collect::<Vec<_>>();
+        assert_eq!(4, pieces.len());
+        assert!(pieces.iter().all(|it| !it.closed));
+
+        let joined = join_contours(pieces);
+        let whole = trace_contours(&grid, grid.cell_rect());
+        assert_eq!(1, joined.len());
+        assert!(joined[0].closed);
+        assert_eq!(whole[0].points.len(), joined[0].points.len());
+
+        let pipeline = ContourPipeline {
+            simplification: Simplification::None,
+            smoothing: Smoothing::CatmullRom { tolerance: 0.01 }
+        };
+        let processed = pipeline.process(&joined);
+        let expected = pipeline.process(&whole);
+        assert!(processed[0].closed);
+        assert_eq!(expected[0].points.len(), processed[0].points.len());
+        assert!(expected[0].points.iter().all(|it| processed[0].points.contains(it)));
+    }
+
+    #[test]
+    pub fn test_join_pieces_starting_at_the_same_point() {
+        // a single empty vertex on the chunk corner, all four crossings land on it
+        let mut grid = WeightGrid::new(17, 17, 1.0, 0.001);
+        grid.weights_mut().iter_mut().for_each(|it| *it = 1.0);
+        grid.set(8, 8, 0.0);
+        let pieces = grid.cell_rect()
+            .chunks(8)
+            .flat_map(|rect| trace_contours(&grid, rect))
+            .collect::<Vec<_>>();
+        assert_eq!(4, pieces.len());
+        assert!(pieces.iter().all(|it| it.points.iter().all(|point| *point == [8.0, 8.0])));
+
+        // one of them continues another's chain, the others can't and stay on their own
+        let joined = join_contours(pieces);
+        assert_eq!(3, joined.len());
+        assert!(joined.iter().all(|it| !it.closed && it.points.iter().all(|point| *point == [8.0, 8.0])));
+    }
+}
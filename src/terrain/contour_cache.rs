@@ -0,0 +1,273 @@
+use crate::terrain::{WeightGrid, CellRect};
+use crate::terrain::contour::{Contour, ContourPipeline, trace_contours, join_contours};
+
+struct CachedChunk {
+    rect: CellRect,
+    /// Raw traced pieces, open where the iso-line crosses the chunk border
+    pieces: Vec<Contour>,
+    dirty: bool
+}
+
+struct CachedContour {
+    contour: Contour,
+    /// Cells covered by the processed contour
+    cells: CellRect,
+    /// Chunks whose pieces were joined into the contour
+    chunks: Vec<usize>
+}
+
+/// Contours kept between frames. Chunks marked dirty are traced again, their pieces are joined
+/// with the neighbouring chunks' ones and only the contours passing through them are processed again
+pub struct ContourCache {
+    chunk_size: usize,
+    chunks_x: usize,
+    pipeline: ContourPipeline,
+    chunks: Vec<CachedChunk>,
+    contours: Vec<CachedContour>,
+    /// Chunks whose contours were joined and processed again by the last `update`
+    reprocessed: Vec<usize>
+}
+
+impl ContourCache {
+    /// Creates a cache for all the cells of `grid` with every chunk dirty
+    pub fn new(grid: &WeightGrid, chunk_size: usize, pipeline: ContourPipeline) -> Self {
+        let chunk_size = chunk_size.max(1);
+        Self {
+            chunk_size,
+            chunks_x: grid.cell_rect().width.div_ceil(chunk_size),
+            pipeline,
+            chunks: grid.cell_rect()
+                .chunks(chunk_size)
+                .map(|rect| CachedChunk { rect, pieces: Vec::new(), dirty: true })
+                .collect(),
+            contours: Vec::new(),
+            reprocessed: Vec::new()
+        }
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn pipeline(&self) -> ContourPipeline {
+        self.pipeline
+    }
+
+    /// Changing the pipeline invalidates every chunk
+    pub fn set_pipeline(&mut self, pipeline: ContourPipeline) {
+        if pipeline != self.pipeline {
+            self.pipeline = pipeline;
+            self.mark_all_dirty();
+        }
+    }
+
+    pub fn mark_dirty(&mut self, rect: CellRect) {
+        if rect.is_empty() {
+            return;
+        }
+        for chunk in self.chunks.iter_mut() {
+            if !chunk.rect.intersection(&rect).is_empty() {
+                chunk.dirty = true;
+            }
+        }
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        self.chunks.iter_mut().for_each(|it| it.dirty = true);
+    }
+
+    /// Retraces the dirty chunks overlapping `area` and returns how many were traced.
+    /// Dirty chunks outside of it wait until they are needed
+    pub fn update(&mut self, grid: &WeightGrid, area: CellRect) -> usize {
+        let mut affected = vec![false; self.chunks.len()];
+        for (id, chunk) in self.chunks.iter_mut().enumerate() {
+            if !chunk.dirty || chunk.rect.intersection(&area).is_empty() {
+                continue;
+            }
+            chunk.pieces = trace_contours(grid, chunk.rect);
+            chunk.dirty = false;
+            affected[id] = true;
+        }
+        let traced = affected.iter().filter(|it| **it).count();
+        self.reprocessed.clear();
+        if traced == 0 {
+            return 0;
+        }
+
+        // a contour through a retraced chunk is joined again from all of its pieces,
+        // which may in turn belong to other contours through the same chunks
+        loop {
+            let mut grown = false;
+            for contour in self.contours.iter() {
+                if !contour.chunks.iter().any(|id| affected[*id]) {
+                    continue;
+                }
+                for &id in contour.chunks.iter() {
+                    grown |= !affected[id];
+                    affected[id] = true;
+                }
+            }
+            if !grown {
+                break;
+            }
+        }
+        self.contours.retain(|it| !it.chunks.iter().any(|id| affected[*id]));
+        self.reprocessed.extend((0..affected.len()).filter(|id| affected[*id]));
+
+        let pieces = self.chunks
+            .iter()
+            .zip(affected.iter())
+            .filter(|(_, affected)| **affected)
+            .flat_map(|(chunk, _)| chunk.pieces.iter().cloned())
+            .collect::<Vec<_>>();
+        let joined = join_contours(pieces);
+        let processed = self.pipeline.process(&joined);
+        for (raw, contour) in joined.iter().zip(processed) {
+            let chunks = self.chunks_touched(grid, raw);
+            let cells = Self::cells_covered(grid, &contour);
+            self.contours.push(CachedContour { contour, cells, chunks });
+        }
+        traced
+    }
+
+    /// Chunks whose closed rectangle contains a point of `contour`
+    fn chunks_touched(&self, grid: &WeightGrid, contour: &Contour) -> Vec<usize> {
+        let chunk_extent = grid.cell_size() * self.chunk_size as f32;
+        let chunks_y = self.chunks.len() / self.chunks_x.max(1);
+        // points on a chunk border belong to the chunks on both sides
+        let chunk_range = |coord: f32, count: usize| {
+            let position = (coord / chunk_extent).max(0.0);
+            let last = (position.floor() as usize).min(count.saturating_sub(1));
+            let first = if position.fract() == 0.0 { last.saturating_sub(1) } else { last };
+            first..=last
+        };
+        let mut chunks = Vec::new();
+        for point in contour.points.iter() {
+            for cy in chunk_range(point[1], chunks_y) {
+                for cx in chunk_range(point[0], self.chunks_x) {
+                    let id = cy * self.chunks_x + cx;
+                    if !chunks.contains(&id) {
+                        chunks.push(id);
+                    }
+                }
+            }
+        }
+        chunks
+    }
+
+    /// Cells under the bounds of `contour`, padded by half a cell so contours along a grid line cover some
+    fn cells_covered(grid: &WeightGrid, contour: &Contour) -> CellRect {
+        let padding = grid.cell_size() / 2.0;
+        let mut min = [f32::MAX, f32::MAX];
+        let mut max = [f32::MIN, f32::MIN];
+        for point in contour.points.iter() {
+            min = [min[0].min(point[0] - padding), min[1].min(point[1] - padding)];
+            max = [max[0].max(point[0] + padding), max[1].max(point[1] + padding)];
+        }
+        if contour.points.is_empty() {
+            return CellRect::new(0, 0, 0, 0);
+        }
+        grid.cells_overlapping(min, max)
+    }
+
+    /// Chunks whose contours were replaced by the last `update`, in chunk order.
+    /// Contours through other chunks kept the same points
+    pub fn reprocessed_chunks(&self) -> &[usize] {
+        &self.reprocessed
+    }
+
+    /// All the contours with the chunks their pieces were traced in. Chunks are numbered row by row
+    /// in the order of `CellRect::chunks` over the whole grid
+    pub fn contours_with_chunks(&self) -> impl Iterator<Item = (&Contour, &[usize])> + '_ {
+        self.contours.iter().map(|it| (&it.contour, it.chunks.as_slice()))
+    }
+
+    /// Contours overlapping `area`
+    pub fn contours(&self, area: CellRect) -> impl Iterator<Item = &Contour> + '_ {
+        self.contours
+            .iter()
+            .filter(move |it| !it.cells.intersection(&area).is_empty())
+            .map(|it| &it.contour)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::{WeightGrid, CellRect};
+    use std::f32::consts::{PI, TAU};
+    use crate::terrain::contour::ContourPipeline;
+    use crate::terrain::smoothing::Smoothing;
+    use crate::terrain::simplify::Simplification;
+    use crate::terrain::contour_cache::ContourCache;
+    use crate::terrain::collision::TerrainCollision;
+    use crate::terrain::shape::Shape;
+
+    #[test]
+    pub fn test_only_dirty_chunks_are_traced() {
+        let mut grid = WeightGrid::new(33, 33, 1.0, 0.001);
+        grid.weights_mut().iter_mut().for_each(|it| *it = 1.0);
+        let pipeline = ContourPipeline::default();
+        let mut cache = ContourCache::new(&grid, 8, pipeline);
+        let mut collision = TerrainCollision::build(&grid, &pipeline, 8);
+        assert_eq!(16, cache.update(&grid, grid.cell_rect()));
+        assert_eq!(0, cache.update(&grid, grid.cell_rect()));
+        assert_eq!(0, cache.contours(grid.cell_rect()).count());
+
+        let dirty = grid.carve(&Shape::Capsule { a: [11.0, 12.0], b: [13.0, 12.0], radius: 1.5 });
+        cache.mark_dirty(dirty);
+        assert_eq!(CellRect::new(8, 9, 8, 6), dirty);
+        // dirty chunks out of view wait until they get into it
+        assert_eq!(0, cache.update(&grid, CellRect::new(0, 0, 8, 8)));
+        assert_eq!(1, cache.update(&grid, grid.cell_rect()));
+        assert_eq!(1, cache.contours(grid.cell_rect()).filter(|it| it.closed).count());
+
+        assert!(collision.raycast([4.0, 12.0], [1.0, 0.0], 100.0).is_none());
+        collision.rebuild(&grid, &pipeline, dirty);
+        let hit = collision.raycast([4.0, 12.0], [1.0, 0.0], 100.0).unwrap();
+        assert!((hit.point[0] - 9.5).abs() < 0.0001);
+    }
+
+    /// Angle between the edges meeting at each point
+    fn turning_angles(points: &[[f32; 2]]) -> Vec<([f32; 2], f32)> {
+        let count = points.len();
+        (0..count)
+            .map(|id| {
+                let (a, b, c) = (points[(id + count - 1) % count], points[id], points[(id + 1) % count]);
+                let incoming = (b[1] - a[1]).atan2(b[0] - a[0]);
+                let outgoing = (c[1] - b[1]).atan2(c[0] - b[0]);
+                let turn = (outgoing - incoming + PI).rem_euclid(TAU) - PI;
+                (b, turn.abs())
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn test_smoothing_is_continuous_across_chunks() {
+        let mut grid = WeightGrid::new(17, 17, 1.0, 0.001);
+        grid.fill(&Shape::Circle { center: [7.3, 7.4], radius: 5.0 });
+        let pipeline = ContourPipeline {
+            simplification: Simplification::None,
+            smoothing: Smoothing::CatmullRom { tolerance: 0.0001 }
+        };
+        let mut cache = ContourCache::new(&grid, 8, pipeline);
+        assert_eq!(4, cache.update(&grid, grid.cell_rect()));
+        let contours = cache.contours(grid.cell_rect()).collect::<Vec<_>>();
+        assert_eq!(1, contours.len());
+        assert!(contours[0].closed);
+
+        // the pieces of the four chunks are smoothed as the loop traced in one go
+        let whole = pipeline.run(&grid, grid.cell_rect());
+        assert_eq!(whole[0].points.len(), contours[0].points.len());
+        assert!(whole[0].points.iter().all(|it| contours[0].points.contains(it)));
+
+        // the spline keeps its tangent through the seams at x = 8 and y = 8, only the short edges around them bend
+        let seams = turning_angles(&contours[0].points)
+            .into_iter()
+            .filter(|(point, _)| point[0] == 8.0 || point[1] == 8.0)
+            .collect::<Vec<_>>();
+        assert_eq!(4, seams.len());
+        for (point, angle) in seams {
+            assert!(angle < 0.05, "{:?} turns by {}", point, angle);
+        }
+    }
+}
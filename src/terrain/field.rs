@@ -0,0 +1,204 @@
+use crate::terrain::WeightGrid;
+use crate::terrain::contour::cell_segments;
+use crate::terrain::collision::{closest_point_on_segment, outward_normal};
+
+/// Closest point of the iso-line to a query point
+#[derive(Copy, Clone, Debug)]
+pub struct SurfacePoint {
+    pub point: [f32; 2],
+    /// Unit normal pointing out of the solid
+    pub normal: [f32; 2],
+    /// Negative inside the solid
+    pub signed_distance: f32
+}
+
+impl WeightGrid {
+    /// Cell containing the world-space `point`, clamped to the grid, and the position inside of it in `0.0..=1.0`
+    fn locate(&self, point: [f32; 2]) -> Option<(usize, usize, f32, f32)> {
+        let cells = self.cell_rect();
+        if cells.is_empty() {
+            return None;
+        }
+        let to_cell = |coord: f32, count: usize| {
+            let scaled = (coord / self.cell_size).clamp(0.0, count as f32);
+            let cell = (scaled.floor() as usize).min(count - 1);
+            (cell, scaled - cell as f32)
+        };
+        let (x, tx) = to_cell(point[0], cells.width);
+        let (y, ty) = to_cell(point[1], cells.height);
+        Some((x, y, tx, ty))
+    }
+
+    /// Bilinear interpolation of the weights at a world-space point, clamped to the grid
+    pub fn sample(&self, point: [f32; 2]) -> f32 {
+        match self.locate(point) {
+            None => 0.0,
+            Some((x, y, tx, ty)) => {
+                let top = self.get(x, y) * (1.0 - tx) + self.get(x + 1, y) * tx;
+                let bottom = self.get(x, y + 1) * (1.0 - tx) + self.get(x + 1, y + 1) * tx;
+                top * (1.0 - ty) + bottom * ty
+            }
+        }
+    }
+
+    /// Derivative of the bilinear interpolation per world unit, points towards heavier weights
+    pub fn gradient(&self, point: [f32; 2]) -> [f32; 2] {
+        match self.locate(point) {
+            None => [0.0, 0.0],
+            Some((x, y, tx, ty)) => {
+                let (w00, w10) = (self.get(x, y), self.get(x + 1, y));
+                let (w01, w11) = (self.get(x, y + 1), self.get(x + 1, y + 1));
+                [
+                    ((w10 - w00) * (1.0 - ty) + (w11 - w01) * ty) / self.cell_size,
+                    ((w01 - w00) * (1.0 - tx) + (w11 - w10) * tx) / self.cell_size
+                ]
+            }
+        }
+    }
+
+    /// Unit normal pointing out of the solid, i.e. against the gradient.
+    /// `None` where the field is flat
+    pub fn normal(&self, point: [f32; 2]) -> Option<[f32; 2]> {
+        let gradient = self.gradient(point);
+        let length = (gradient[0] * gradient[0] + gradient[1] * gradient[1]).sqrt();
+        if length < f32::EPSILON {
+            None
+        } else {
+            Some([-gradient[0] / length, -gradient[1] / length])
+        }
+    }
+
+    /// Closest point of the traced iso-line within `max_distance` of `point`.
+    /// Searches rings of cells around the point, so the cost grows with the distance to the surface
+    pub fn closest_surface(&self, point: [f32; 2], max_distance: f32) -> Option<SurfacePoint> {
+        let (cell_x, cell_y, _, _) = self.locate(point)?;
+        let cells = self.cell_rect();
+        let max_ring = (max_distance / self.cell_size).ceil() as usize + 1;
+
+        let mut best_distance = max_distance;
+        let mut closest: Vec<([f32; 2], [f32; 2])> = Vec::new();
+        for ring in 0..=max_ring {
+            if !closest.is_empty() && best_distance <= ring.saturating_sub(1) as f32 * self.cell_size {
+                break;
+            }
+            let (first_x, first_y) = (cell_x.saturating_sub(ring), cell_y.saturating_sub(ring));
+            let last_x = (cell_x + ring).min(cells.width - 1);
+            let last_y = (cell_y + ring).min(cells.height - 1);
+            for y in first_y..=last_y {
+                for x in first_x..=last_x {
+                    if x.abs_diff(cell_x).max(y.abs_diff(cell_y)) != ring {
+                        continue;
+                    }
+                    for segment in cell_segments(self, x, y).iter().flatten() {
+                        let on_segment = closest_point_on_segment(point, segment.start, segment.end);
+                        let offset = [point[0] - on_segment[0], point[1] - on_segment[1]];
+                        let distance = (offset[0] * offset[0] + offset[1] * offset[1]).sqrt();
+                        if distance < best_distance - SURFACE_EPSILON {
+                            best_distance = distance;
+                            closest.clear();
+                        }
+                        if distance <= best_distance + SURFACE_EPSILON {
+                            closest.push((on_segment, outward_normal(segment.start, segment.end)));
+                        }
+                    }
+                }
+            }
+        }
+
+        let &(surface_point, _) = closest.first()?;
+        // segments meeting at the closest point vote together, which resolves corners
+        let summed = closest
+            .iter()
+            .fold([0.0, 0.0], |sum, (_, normal)| [sum[0] + normal[0], sum[1] + normal[1]]);
+        let offset = [point[0] - surface_point[0], point[1] - surface_point[1]];
+        let inside = offset[0] * summed[0] + offset[1] * summed[1] < 0.0;
+        let length = (summed[0] * summed[0] + summed[1] * summed[1]).sqrt().max(f32::EPSILON);
+        Some(SurfacePoint {
+            point: surface_point,
+            normal: [summed[0] / length, summed[1] / length],
+            signed_distance: if inside { -best_distance } else { best_distance }
+        })
+    }
+
+    /// Distance to the traced iso-line, negative inside the solid.
+    /// Points farther than `max_distance` from the surface get `±max_distance`
+    pub fn signed_distance(&self, point: [f32; 2], max_distance: f32) -> f32 {
+        match self.closest_surface(point, max_distance) {
+            Some(surface) => surface.signed_distance,
+            None if self.sample(point) >= self.iso_level => -max_distance,
+            None => max_distance
+        }
+    }
+}
+
+const SURFACE_EPSILON: f32 = 0.0001;
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::WeightGrid;
+
+    const CENTER: [f32; 2] = [200.0, 200.0];
+    const RADIUS: f32 = 95.0;
+
+    /// Circle stored as `clamp(-distance / cell_size, 0, 1)`
+    fn circle() -> WeightGrid {
+        let mut grid = WeightGrid::new(41, 41, 10.0, 0.001);
+        for y in 0..41 {
+            for x in 0..41 {
+                let [px, py] = grid.vertex_position(x, y);
+                let distance = ((px - CENTER[0]).powi(2) + (py - CENTER[1]).powi(2)).sqrt() - RADIUS;
+                grid.set(x, y, (-distance / grid.cell_size()).clamp(0.0, 1.0));
+            }
+        }
+        grid
+    }
+
+    fn at_angle(theta: f32, radius: f32) -> [f32; 2] {
+        [CENTER[0] + theta.cos() * radius, CENTER[1] + theta.sin() * radius]
+    }
+
+    #[test]
+    pub fn test_sample_interpolates_vertices() {
+        let grid = circle();
+        assert_eq!(grid.get(20, 20), grid.sample([200.0, 200.0]));
+        assert_eq!(grid.get(3, 2), grid.sample([30.0, 20.0]));
+        let mid = grid.sample([195.0, 92.0]);
+        let expected = (grid.get(19, 9) * 0.5 + grid.get(20, 9) * 0.5) * 0.8
+            + (grid.get(19, 10) * 0.5 + grid.get(20, 10) * 0.5) * 0.2;
+        assert!((mid - expected).abs() < 0.0001);
+        assert_eq!(0.0, grid.sample([-50.0, -50.0]));
+    }
+
+    #[test]
+    pub fn test_circle_signed_distance() {
+        let grid = circle();
+        for step in 0..32 {
+            let theta = step as f32 / 32.0 * std::f32::consts::TAU;
+            for offset in [-30.0, -4.0, 3.0, 25.0] {
+                let distance = grid.signed_distance(at_angle(theta, RADIUS + offset), 100.0);
+                assert!(
+                    (distance - offset).abs() < 2.5,
+                    "distance {} at offset {} and angle {}", distance, offset, theta
+                );
+            }
+        }
+        assert_eq!(-50.0, grid.signed_distance(CENTER, 50.0));
+        assert_eq!(50.0, grid.signed_distance([10.0, 10.0], 50.0));
+    }
+
+    #[test]
+    pub fn test_circle_normals_point_outwards() {
+        let grid = circle();
+        for step in 0..16 {
+            let theta = step as f32 / 16.0 * std::f32::consts::TAU;
+            let radial = [theta.cos(), theta.sin()];
+
+            let normal = grid.normal(at_angle(theta, RADIUS - 5.0)).unwrap();
+            assert!(normal[0] * radial[0] + normal[1] * radial[1] > 0.9);
+
+            let surface = grid.closest_surface(at_angle(theta, RADIUS + 6.0), 50.0).unwrap();
+            assert!(surface.normal[0] * radial[0] + surface.normal[1] * radial[1] > 0.9);
+        }
+        assert!(grid.normal(CENTER).is_none());
+    }
+}
@@ -0,0 +1,143 @@
+use crate::terrain::{WeightGrid, CellRect, Connectivity};
+use crate::terrain::contour::trace_contours;
+use crate::terrain::regions::RegionLabels;
+use crate::terrain::measure::signed_area;
+
+/// What holds the solid in place. Solid regions reaching none of the anchors float
+#[derive(Clone, Debug, Default)]
+pub struct Anchors {
+    /// Regions touching the border of the grid are anchored
+    pub border: bool,
+    /// Regions containing any of these vertices are anchored
+    pub vertices: Vec<(usize, usize)>
+}
+
+/// Solid region cut out of the terrain
+#[derive(Clone)]
+pub struct DetachedIsland {
+    /// Weights of the region with an empty margin of one vertex around it
+    pub field: WeightGrid,
+    /// World-space position of the vertex (0, 0) of `field`
+    pub origin: [f32; 2],
+    /// Cells of the terrain whose contours changed
+    pub dirty: CellRect
+}
+
+impl DetachedIsland {
+    /// Outer loop of the island in world space
+    pub fn outline(&self) -> Option<Vec<[f32; 2]>> {
+        trace_contours(&self.field, self.field.cell_rect())
+            .into_iter()
+            .filter(|it| it.closed)
+            .map(|it| (signed_area(&it.points), it.points))
+            .filter(|(area, _)| *area > 0.0)
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+            .map(|(_, points)| {
+                points
+                    .into_iter()
+                    .map(|it| [it[0] + self.origin[0], it[1] + self.origin[1]])
+                    .collect()
+            })
+    }
+}
+
+impl WeightGrid {
+    /// Ids of the solid regions of `labels` which reach none of the anchors
+    pub fn floating_regions(&self, labels: &RegionLabels, anchors: &Anchors) -> Vec<usize> {
+        let mut anchored = labels
+            .regions()
+            .iter()
+            .map(|it| !it.solid || (anchors.border && it.touches_border))
+            .collect::<Vec<_>>();
+        for &(x, y) in anchors.vertices.iter() {
+            if x < self.width() && y < self.height() {
+                anchored[labels.region_at(x, y)] = true;
+            }
+        }
+        (0..anchored.len()).filter(|id| !anchored[*id]).collect()
+    }
+
+    /// Removes the floating solid regions and returns them as separate fields.
+    /// Regions are 4-connected like the traced contours
+    pub fn detach_floating_islands(&mut self, anchors: &Anchors) -> Vec<DetachedIsland> {
+        let labels = RegionLabels::label(self, Connectivity::Four);
+        let mut islands = Vec::new();
+        for id in self.floating_regions(&labels, anchors) {
+            let region = &labels.regions()[id];
+            let (width, height) = (region.max.0 - region.min.0 + 3, region.max.1 - region.min.1 + 3);
+            let mut field = WeightGrid::new(width, height, self.cell_size(), self.iso_level());
+            for y in region.min.1..=region.max.1 {
+                for x in region.min.0..=region.max.0 {
+                    if labels.region_at(x, y) == id {
+                        field.set(x - region.min.0 + 1, y - region.min.1 + 1, self.get(x, y));
+                        self.set(x, y, 0.0);
+                    }
+                }
+            }
+            islands.push(DetachedIsland {
+                field,
+                origin: [
+                    (region.min.0 as f32 - 1.0) * self.cell_size(),
+                    (region.min.1 as f32 - 1.0) * self.cell_size()
+                ],
+                dirty: self.cells_around_vertices(region.min.0..region.max.0 + 1, region.min.1..region.max.1 + 1)
+            });
+        }
+        islands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::WeightGrid;
+    use crate::terrain::islands::Anchors;
+    use crate::terrain::measure::signed_area;
+
+    /// Ground along the bottom border, a floating block and a floating vertex
+    fn map() -> WeightGrid {
+        let mut grid = WeightGrid::new(16, 12, 2.0, 0.001);
+        for x in 0..16 {
+            grid.set(x, 11, 1.0);
+            grid.set(x, 10, 0.5);
+        }
+        for y in 3..=5 {
+            for x in 4..=7 {
+                grid.set(x, y, 0.5);
+            }
+        }
+        grid.set(12, 2, 0.5);
+        grid
+    }
+
+    #[test]
+    pub fn test_detach_floating_islands() {
+        let mut grid = map();
+        let anchors = Anchors { border: true, vertices: vec![] };
+        let islands = grid.detach_floating_islands(&anchors);
+        assert_eq!(2, islands.len());
+        assert!((4..=7).all(|x| !grid.is_solid(x, 4)) && !grid.is_solid(12, 2));
+        assert!(grid.is_solid(3, 10) && grid.is_solid(3, 11));
+
+        let block = islands.iter().find(|it| it.field.width() == 6).unwrap();
+        assert_eq!((6, 5), (block.field.width(), block.field.height()));
+        assert_eq!([6.0, 4.0], block.origin);
+        assert!(block.dirty.contains(3, 2) && block.dirty.contains(7, 5));
+        let outline = block.outline().unwrap();
+        // 3 x 2 cells widened by half a cell on every side
+        assert!((signed_area(&outline) - (6.0 + 2.0) * (4.0 + 2.0) + 4.0 * 0.5).abs() < 0.001);
+        let min_x = outline.iter().map(|it| it[0]).fold(f32::MAX, f32::min);
+        let max_y = outline.iter().map(|it| it[1]).fold(f32::MIN, f32::max);
+        assert_eq!((7.0, 11.0), (min_x, max_y));
+
+        assert!(grid.detach_floating_islands(&anchors).is_empty());
+    }
+
+    #[test]
+    pub fn test_anchor_vertices() {
+        let mut grid = map();
+        let anchors = Anchors { border: false, vertices: vec![(5, 5), (0, 11)] };
+        let islands = grid.detach_floating_islands(&anchors);
+        assert_eq!(1, islands.len());
+        assert!(grid.is_solid(5, 4) && !grid.is_solid(12, 2));
+    }
+}
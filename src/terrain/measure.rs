@@ -0,0 +1,197 @@
+use crate::terrain::{WeightGrid, CellRect, Connectivity};
+use crate::terrain::contour::trace_contours;
+use crate::terrain::regions::RegionLabels;
+
+/// Shoelace area, positive for loops winding around solid and negative for holes
+pub fn signed_area(points: &[[f32; 2]]) -> f32 {
+    let mut area = 0.0;
+    for (id, a) in points.iter().enumerate() {
+        let b = points[(id + 1) % points.len()];
+        area += a[0] * b[1] - b[0] * a[1];
+    }
+    area / 2.0
+}
+
+pub fn perimeter(points: &[[f32; 2]], closed: bool) -> f32 {
+    let length = |a: [f32; 2], b: [f32; 2]| ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+    let open_length = points.windows(2).map(|it| length(it[0], it[1])).sum::<f32>();
+    match (closed, points.first(), points.last()) {
+        (true, Some(first), Some(last)) => open_length + length(*last, *first),
+        _ => open_length
+    }
+}
+
+/// Area centroid of a closed loop, the mean of the points for a degenerate one
+pub fn centroid(points: &[[f32; 2]]) -> Option<[f32; 2]> {
+    if points.is_empty() {
+        return None;
+    }
+    let area = signed_area(points);
+    if area.abs() < f32::EPSILON {
+        let count = points.len() as f32;
+        let sum = points.iter().fold([0.0, 0.0], |sum, it| [sum[0] + it[0], sum[1] + it[1]]);
+        return Some([sum[0] / count, sum[1] / count]);
+    }
+    let mut sum = [0.0, 0.0];
+    for (id, a) in points.iter().enumerate() {
+        let b = points[(id + 1) % points.len()];
+        let cross = a[0] * b[1] - b[0] * a[1];
+        sum = [sum[0] + (a[0] + b[0]) * cross, sum[1] + (a[1] + b[1]) * cross];
+    }
+    Some([sum[0] / (6.0 * area), sum[1] / (6.0 * area)])
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LoopMeasure {
+    pub signed_area: f32,
+    pub perimeter: f32,
+    pub centroid: [f32; 2]
+}
+
+/// Area, length and centroid of a closed contour loop
+pub fn measure_loop(points: &[[f32; 2]]) -> Option<LoopMeasure> {
+    Some(LoopMeasure {
+        signed_area: signed_area(points),
+        perimeter: perimeter(points, true),
+        centroid: centroid(points)?
+    })
+}
+
+impl WeightGrid {
+    /// Area of the solid inside `rect` as bounded by the interpolated iso-line,
+    /// including the fractional parts of the cells it crosses
+    pub fn solid_area(&self, rect: CellRect) -> f32 {
+        let rect = rect.intersection(&self.cell_rect());
+        let mut area = 0.0;
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                area += self.cell_solid_area(x, y);
+            }
+        }
+        area
+    }
+
+    fn cell_solid_area(&self, x: usize, y: usize) -> f32 {
+        self.cell_solid_polygons(x, y).iter().map(|it| signed_area(it).abs()).sum()
+    }
+
+    /// Convex polygons covering the solid part of cell `(x, y)` as cut by the iso-line,
+    /// two corner triangles for a saddle cell
+    pub fn cell_solid_polygons(&self, x: usize, y: usize) -> Vec<Vec<[f32; 2]>> {
+        let corners = [(x, y), (x + 1, y), (x + 1, y + 1), (x, y + 1)];
+        let solid = corners.map(|(x, y)| self.is_solid(x, y));
+        let position = |id: usize| self.vertex_position(corners[id].0, corners[id].1);
+        let crossing = |from: usize, to: usize| {
+            let (solid_corner, empty_corner) = if solid[from] { (from, to) } else { (to, from) };
+            let (sx, sy) = corners[solid_corner];
+            let extent = self.get(sx, sy).clamp(0.0, 1.0);
+            let (p, q) = (position(solid_corner), position(empty_corner));
+            [p[0] + (q[0] - p[0]) * extent, p[1] + (q[1] - p[1]) * extent]
+        };
+
+        // the contour keeps diagonal solid corners of a saddle apart, each one cuts off a triangle
+        if solid[0] == solid[2] && solid[1] == solid[3] && solid[0] != solid[1] {
+            return (0..4)
+                .filter(|id| solid[*id])
+                .map(|id| {
+                    let (prev, next) = ((id + 3) % 4, (id + 1) % 4);
+                    vec![crossing(prev, id), position(id), crossing(id, next)]
+                })
+                .collect();
+        }
+
+        let mut polygon = Vec::with_capacity(8);
+        for id in 0..4 {
+            let next = (id + 1) % 4;
+            if solid[id] {
+                polygon.push(position(id));
+            }
+            if solid[id] != solid[next] {
+                polygon.push(crossing(id, next));
+            }
+        }
+        if polygon.len() < 3 {
+            Vec::new()
+        } else {
+            vec![polygon]
+        }
+    }
+}
+
+/// Summary of the whole terrain for the editor status readout
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TerrainStats {
+    pub land_area: f32,
+    /// Length of all the contours, including the ones cut by the map border
+    pub coastline_length: f32,
+    pub landmasses: usize,
+    pub lakes: usize,
+    pub outer_loops: usize,
+    pub holes: usize
+}
+
+impl TerrainStats {
+    pub fn measure(grid: &WeightGrid) -> Self {
+        let contours = trace_contours(grid, grid.cell_rect());
+        let labels = RegionLabels::label(grid, Connectivity::Four);
+        let closed = contours.iter().filter(|it| it.closed);
+        Self {
+            land_area: grid.solid_area(grid.cell_rect()),
+            coastline_length: contours.iter().map(|it| perimeter(&it.points, it.closed)).sum(),
+            landmasses: labels.landmasses().count(),
+            lakes: labels.lakes().count(),
+            outer_loops: closed.clone().filter(|it| signed_area(&it.points) > 0.0).count(),
+            holes: closed.filter(|it| signed_area(&it.points) < 0.0).count()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::{WeightGrid, CellRect};
+    use crate::terrain::contour::trace_contours;
+    use crate::terrain::shape::Shape;
+    use crate::terrain::measure::{measure_loop, perimeter, signed_area, TerrainStats};
+
+    #[test]
+    pub fn test_loop_measures() {
+        let square = [[0.0, 0.0], [0.0, 2.0], [4.0, 2.0], [4.0, 0.0]];
+        let measure = measure_loop(&square).unwrap();
+        assert_eq!(-8.0, measure.signed_area);
+        assert_eq!(12.0, measure.perimeter);
+        assert_eq!([2.0, 1.0], measure.centroid);
+        assert_eq!(8.0, perimeter(&square, false));
+
+        let reversed = square.iter().rev().copied().collect::<Vec<_>>();
+        assert_eq!(8.0, signed_area(&reversed));
+        assert_eq!([2.0, 1.0], measure_loop(&reversed).unwrap().centroid);
+        assert!(measure_loop(&[]).is_none());
+    }
+
+    #[test]
+    pub fn test_field_area_matches_loops() {
+        let mut grid = WeightGrid::new(33, 33, 1.0, 0.001);
+        grid.fill(&Shape::Circle { center: [16.0, 16.0], radius: 10.0 });
+        grid.carve(&Shape::Circle { center: [14.0, 15.0], radius: 3.0 });
+        grid.set(28, 4, 0.5);
+        grid.set(29, 5, 0.5);
+
+        let contours = trace_contours(&grid, grid.cell_rect());
+        let loops = contours.iter().map(|it| signed_area(&it.points)).sum::<f32>();
+        let area = grid.solid_area(grid.cell_rect());
+        assert!((area - loops).abs() < 0.01);
+        let expected = std::f32::consts::PI * (100.0 - 9.0) + 2.0 * 0.5 * 0.5 * 2.0;
+        // the iso-line cuts chords across the curved outlines
+        assert!((area - expected).abs() / expected < 0.03);
+
+        let left = grid.solid_area(CellRect::new(0, 0, 13, 32));
+        let right = grid.solid_area(CellRect::new(13, 0, 19, 32));
+        assert!((left + right - area).abs() < 0.01);
+
+        let stats = TerrainStats::measure(&grid);
+        assert_eq!((3, 1), (stats.landmasses, stats.lakes));
+        assert_eq!((3, 1), (stats.outer_loops, stats.holes));
+        let coastline = 2.0 * std::f32::consts::PI * 13.0 + 2.0 * 4.0 * 0.5f32.hypot(0.5);
+        assert!((stats.coastline_length - coastline).abs() / coastline < 0.02);
+    }
+}
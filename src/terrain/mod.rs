@@ -0,0 +1,230 @@
+pub mod contour;
+pub mod smoothing;
+pub mod simplify;
+pub mod collision;
+pub mod field;
+pub mod raycast;
+pub mod shape;
+pub mod contour_cache;
+pub mod regions;
+pub mod measure;
+pub mod islands;
+pub mod sdf;
+
+use std::ops::Range;
+
+/// Rectangle of grid cells. Cell (x, y) spans vertices (x, y)..=(x + 1, y + 1)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CellRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize
+}
+
+impl CellRect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+
+    pub fn intersection(&self, rhs: &CellRect) -> CellRect {
+        let x = self.x.max(rhs.x);
+        let y = self.y.max(rhs.y);
+        let right = (self.x + self.width).min(rhs.x + rhs.width);
+        let bottom = (self.y + self.height).min(rhs.y + rhs.height);
+        if right <= x || bottom <= y {
+            CellRect::new(x, y, 0, 0)
+        } else {
+            CellRect::new(x, y, right - x, bottom - y)
+        }
+    }
+
+    /// Vertices at the corners of the cells, as ranges of x and y indices
+    pub fn vertex_ranges(&self) -> (Range<usize>, Range<usize>) {
+        if self.is_empty() {
+            return (self.x..self.x, self.y..self.y);
+        }
+        (self.x..self.x + self.width + 1, self.y..self.y + self.height + 1)
+    }
+
+    /// Splits the rectangle into chunks of at most `chunk_size` cells per side, aligned to multiples of `chunk_size`
+    pub fn chunks(&self, chunk_size: usize) -> impl Iterator<Item = CellRect> + '_ {
+        let chunk_size = chunk_size.max(1);
+        let (first_x, first_y) = (self.x / chunk_size, self.y / chunk_size);
+        let last_x = (self.x + self.width).div_ceil(chunk_size);
+        let last_y = (self.y + self.height).div_ceil(chunk_size);
+        (first_y..last_y)
+            .flat_map(move |cy| (first_x..last_x).map(move |cx| (cx, cy)))
+            .map(move |(cx, cy)| {
+                CellRect::new(cx * chunk_size, cy * chunk_size, chunk_size, chunk_size)
+                    .intersection(self)
+            })
+            .filter(|it| !it.is_empty())
+    }
+}
+
+/// Which neighbours of a grid vertex count as adjacent
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only the vertices sharing an edge
+    Four,
+    /// Diagonal vertices as well
+    Eight
+}
+
+impl Connectivity {
+    /// Offsets to the adjacent vertices, the axis-aligned ones first
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        const OFFSETS: [(isize, isize); 8] = [
+            (1, 0), (0, 1), (-1, 0), (0, -1),
+            (1, 1), (-1, 1), (-1, -1), (1, -1)
+        ];
+        match self {
+            Connectivity::Four => &OFFSETS[..4],
+            Connectivity::Eight => &OFFSETS
+        }
+    }
+}
+
+/// Scalar field sampled at grid vertices. Vertex (x, y) is placed at `(x * cell_size, y * cell_size)`.
+/// A vertex is solid when its weight reaches `iso_level`; the weight of a solid vertex tells
+/// which fraction of an adjacent edge the solid extends over towards an empty neighbour
+#[derive(Clone)]
+pub struct WeightGrid {
+    width: usize,
+    height: usize,
+    cell_size: f32,
+    iso_level: f32,
+    weights: Vec<f32>
+}
+
+impl WeightGrid {
+    /// Creates a grid of `width` x `height` vertices filled with zero weights
+    pub fn new(width: usize, height: usize, cell_size: f32, iso_level: f32) -> Self {
+        Self {
+            width,
+            height,
+            cell_size,
+            iso_level,
+            weights: vec![0.0; width * height]
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn iso_level(&self) -> f32 {
+        self.iso_level
+    }
+
+    pub fn set_iso_level(&mut self, iso_level: f32) {
+        self.iso_level = iso_level;
+    }
+
+    /// All the cells of the grid
+    pub fn cell_rect(&self) -> CellRect {
+        CellRect::new(
+            0,
+            0,
+            self.width.saturating_sub(1),
+            self.height.saturating_sub(1)
+        )
+    }
+
+    /// Cells overlapping the world-space rectangle from `min` to `max`, clamped to the grid
+    pub fn cells_overlapping(&self, min: [f32; 2], max: [f32; 2]) -> CellRect {
+        let cells = self.cell_rect();
+        let to_cell = |coord: f32, count: usize| (coord / self.cell_size).max(0.0).min(count as f32);
+        let first_x = to_cell(min[0], cells.width).floor() as usize;
+        let first_y = to_cell(min[1], cells.height).floor() as usize;
+        let last_x = to_cell(max[0], cells.width).ceil() as usize;
+        let last_y = to_cell(max[1], cells.height).ceil() as usize;
+        CellRect::new(
+            first_x,
+            first_y,
+            last_x.saturating_sub(first_x),
+            last_y.saturating_sub(first_y)
+        )
+    }
+
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.weights[self.index(x, y)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, weight: f32) {
+        let index = self.index(x, y);
+        self.weights[index] = weight;
+    }
+
+    pub fn is_solid(&self, x: usize, y: usize) -> bool {
+        self.get(x, y) >= self.iso_level
+    }
+
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+
+    pub fn weights_mut(&mut self) -> &mut [f32] {
+        &mut self.weights
+    }
+
+    pub fn vertex_position(&self, x: usize, y: usize) -> [f32; 2] {
+        [x as f32 * self.cell_size, y as f32 * self.cell_size]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::{CellRect, WeightGrid};
+
+    #[test]
+    pub fn test_chunks_cover_rect() {
+        let rect = CellRect::new(5, 3, 30, 14);
+        let chunks = rect.chunks(16).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                CellRect::new(5, 3, 11, 13),
+                CellRect::new(16, 3, 16, 13),
+                CellRect::new(32, 3, 3, 13),
+                CellRect::new(5, 16, 11, 1),
+                CellRect::new(16, 16, 16, 1),
+                CellRect::new(32, 16, 3, 1),
+            ],
+            chunks
+        );
+        let area: usize = chunks.iter().map(|it| it.width * it.height).sum();
+        assert_eq!(30 * 14, area);
+    }
+
+    #[test]
+    pub fn test_cells_overlapping() {
+        let grid = WeightGrid::new(9, 5, 10.0, 0.001);
+        assert_eq!(CellRect::new(1, 0, 3, 2), grid.cells_overlapping([15.0, -30.0], [32.0, 20.0]));
+        assert_eq!(grid.cell_rect(), grid.cells_overlapping([-100.0, -100.0], [1000.0, 1000.0]));
+        assert!(grid.cells_overlapping([100.0, 0.0], [200.0, 10.0]).is_empty());
+
+        let (xs, ys) = CellRect::new(1, 0, 3, 2).vertex_ranges();
+        assert_eq!((1..5, 0..3), (xs, ys));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::terrain::WeightGrid;
+use crate::terrain::contour::cell_segments;
+use crate::terrain::collision::{outward_normal, ray_segment_distance};
+
+#[derive(Copy, Clone, Debug)]
+pub struct GridRayHit {
+    pub point: [f32; 2],
+    /// Surface normal pointing out of the solid
+    pub normal: [f32; 2],
+    /// Cell whose iso-line segment was hit
+    pub cell: (usize, usize),
+    pub distance: f32
+}
+
+impl WeightGrid {
+    /// Walks the cells along the ray with a DDA and returns the first crossing of the interpolated iso-line.
+    /// Segments are hit from both sides, so a ray starting in the solid hits the surface on its way out
+    pub fn raycast(&self, origin: [f32; 2], dir: [f32; 2], max_distance: f32) -> Option<GridRayHit> {
+        let length = (dir[0] * dir[0] + dir[1] * dir[1]).sqrt();
+        let cells = self.cell_rect();
+        if length < f32::EPSILON || cells.is_empty() {
+            return None;
+        }
+        let dir = [dir[0] / length, dir[1] / length];
+        let grid_max = self.vertex_position(cells.width, cells.height);
+        let (t_enter, t_exit) = clip_ray(origin, dir, [0.0, 0.0], grid_max)?;
+        let t_exit = t_exit.min(max_distance);
+        if t_enter > t_exit {
+            return None;
+        }
+
+        let entry = [origin[0] + dir[0] * t_enter, origin[1] + dir[1] * t_enter];
+        let to_cell = |coord: f32, count: usize| ((coord / self.cell_size).floor().max(0.0) as usize).min(count - 1);
+        let mut cell = [to_cell(entry[0], cells.width), to_cell(entry[1], cells.height)];
+        let counts = [cells.width, cells.height];
+
+        let mut step = [0isize; 2];
+        let mut t_next = [f32::MAX; 2];
+        let mut t_delta = [f32::MAX; 2];
+        for axis in 0..2 {
+            if dir[axis].abs() < f32::EPSILON {
+                continue;
+            }
+            step[axis] = if dir[axis] > 0.0 { 1 } else { -1 };
+            let boundary = if dir[axis] > 0.0 { cell[axis] + 1 } else { cell[axis] };
+            t_next[axis] = (boundary as f32 * self.cell_size - origin[axis]) / dir[axis];
+            t_delta[axis] = self.cell_size / dir[axis].abs();
+        }
+
+        loop {
+            let segments = cell_segments(self, cell[0], cell[1]);
+            let closest = segments
+                .iter()
+                .flatten()
+                .filter_map(|segment| {
+                    ray_segment_distance(origin, dir, segment.start, segment.end)
+                        .filter(|distance| *distance >= t_enter && *distance <= t_exit)
+                        .map(|distance| (distance, segment))
+                })
+                .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            // segments stay inside their cell, so the first cell with a hit holds the closest one
+            if let Some((distance, segment)) = closest {
+                return Some(GridRayHit {
+                    point: [origin[0] + dir[0] * distance, origin[1] + dir[1] * distance],
+                    normal: outward_normal(segment.start, segment.end),
+                    cell: (cell[0], cell[1]),
+                    distance
+                });
+            }
+
+            let axis = if t_next[0] < t_next[1] { 0 } else { 1 };
+            if t_next[axis] > t_exit {
+                return None;
+            }
+            let next = cell[axis] as isize + step[axis];
+            if next < 0 || next >= counts[axis] as isize {
+                return None;
+            }
+            cell[axis] = next as usize;
+            t_next[axis] += t_delta[axis];
+        }
+    }
+
+    /// Whether nothing solid blocks the straight line between two world-space points
+    pub fn line_of_sight(&self, from: [f32; 2], to: [f32; 2]) -> bool {
+        let dir = [to[0] - from[0], to[1] - from[1]];
+        let distance = (dir[0] * dir[0] + dir[1] * dir[1]).sqrt();
+        self.raycast(from, dir, distance).is_none()
+    }
+}
+
+/// Distances at which the ray enters and leaves an axis-aligned box
+fn clip_ray(origin: [f32; 2], dir: [f32; 2], min: [f32; 2], max: [f32; 2]) -> Option<(f32, f32)> {
+    let (mut near, mut far) = (0.0f32, f32::MAX);
+    for axis in 0..2 {
+        if dir[axis].abs() < f32::EPSILON {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t0 = (min[axis] - origin[axis]) / dir[axis];
+        let t1 = (max[axis] - origin[axis]) / dir[axis];
+        near = near.max(t0.min(t1));
+        far = far.min(t0.max(t1));
+    }
+    if near <= far {
+        Some((near, far))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::WeightGrid;
+
+    /// Two solid columns of vertices at x = 4 and x = 9 with different extents
+    fn walls() -> WeightGrid {
+        let mut grid = WeightGrid::new(13, 9, 2.0, 0.001);
+        for y in 0..9 {
+            grid.set(4, y, 0.5);
+            grid.set(9, y, 0.25);
+        }
+        grid
+    }
+
+    #[test]
+    pub fn test_hits_interpolated_contour() {
+        let grid = walls();
+        let hit = grid.raycast([1.0, 5.0], [1.0, 0.0], 100.0).unwrap();
+        assert!((hit.point[0] - 7.0).abs() < 0.0001 && (hit.point[1] - 5.0).abs() < 0.0001);
+        assert_eq!((3, 2), hit.cell);
+        assert!((hit.normal[0] + 1.0).abs() < 0.0001);
+        assert!((hit.distance - 6.0).abs() < 0.0001);
+
+        let back = grid.raycast([23.0, 3.0], [-2.0, 0.0], 100.0).unwrap();
+        assert!((back.point[0] - 18.5).abs() < 0.0001);
+        assert_eq!((9, 1), back.cell);
+        assert!((back.normal[0] - 1.0).abs() < 0.0001);
+
+        // starting inside the solid the ray hits the surface on its way out
+        let inside = grid.raycast([8.0, 3.0], [1.0, 0.0], 100.0).unwrap();
+        assert!((inside.point[0] - 9.0).abs() < 0.0001);
+        assert!((inside.normal[0] - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    pub fn test_diagonal_and_outside_rays() {
+        let grid = walls();
+        let hit = grid.raycast([-10.0, -2.5], [1.0, 1.0], 100.0).unwrap();
+        assert!((hit.point[0] - 7.0).abs() < 0.0001 && (hit.point[1] - 14.5).abs() < 0.0001);
+        assert_eq!((3, 7), hit.cell);
+
+        assert!(grid.raycast([1.0, 5.0], [1.0, 0.0], 5.0).is_none());
+        assert!(grid.raycast([1.0, 5.0], [0.0, 1.0], 100.0).is_none());
+        assert!(grid.raycast([1.0, -5.0], [1.0, 0.0], 100.0).is_none());
+
+        assert!(grid.line_of_sight([1.0, 1.0], [1.0, 15.0]));
+        assert!(!grid.line_of_sight([1.0, 1.0], [12.0, 1.0]));
+    }
+}
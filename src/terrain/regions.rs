@@ -0,0 +1,257 @@
+use crate::terrain::{WeightGrid, Connectivity};
+use crate::terrain::contour::Contour;
+use crate::terrain::measure::signed_area;
+
+/// Connected set of grid vertices that are all solid or all empty
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub solid: bool,
+    pub vertex_count: usize,
+    /// Bounding box of the vertices, both corners inclusive
+    pub min: (usize, usize),
+    pub max: (usize, usize),
+    /// Mean world-space position of the vertices
+    pub centroid: [f32; 2],
+    pub touches_border: bool
+}
+
+/// Which part of a region boundary a contour loop is
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoopKind {
+    /// Closed loop around a solid region
+    Outer,
+    /// Closed loop around an empty region enclosed by a solid one
+    Hole,
+    /// Contour cut by the border of the traced area
+    Open
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LoopRegion {
+    pub kind: LoopKind,
+    /// Solid region on the left of the loop
+    pub solid_region: usize,
+    /// Empty region on the right of the loop
+    pub empty_region: usize
+}
+
+/// Region id of every grid vertex
+pub struct RegionLabels {
+    width: usize,
+    labels: Vec<usize>,
+    regions: Vec<Region>
+}
+
+impl RegionLabels {
+    /// Flood fills the thresholded grid. Solid regions use `solid_connectivity` and empty ones the other kind,
+    /// so two regions never cross each other. The contour extractor keeps diagonal solid vertices apart,
+    /// `Connectivity::Four` matches its loops
+    pub fn label(grid: &WeightGrid, solid_connectivity: Connectivity) -> Self {
+        let (width, height) = (grid.width(), grid.height());
+        let empty_connectivity = match solid_connectivity {
+            Connectivity::Four => Connectivity::Eight,
+            Connectivity::Eight => Connectivity::Four
+        };
+        let mut labels = vec![usize::MAX; width * height];
+        let mut regions = Vec::new();
+        let mut stack = Vec::new();
+
+        for first in 0..labels.len() {
+            if labels[first] != usize::MAX {
+                continue;
+            }
+            let (first_x, first_y) = (first % width, first / width);
+            let solid = grid.is_solid(first_x, first_y);
+            let connectivity = if solid { solid_connectivity } else { empty_connectivity };
+            let id = regions.len();
+            let mut region = Region {
+                solid,
+                vertex_count: 0,
+                min: (first_x, first_y),
+                max: (first_x, first_y),
+                centroid: [0.0, 0.0],
+                touches_border: false
+            };
+
+            labels[first] = id;
+            stack.push(first);
+            while let Some(current) = stack.pop() {
+                let (x, y) = (current % width, current / width);
+                region.vertex_count += 1;
+                region.min = (region.min.0.min(x), region.min.1.min(y));
+                region.max = (region.max.0.max(x), region.max.1.max(y));
+                let position = grid.vertex_position(x, y);
+                region.centroid = [region.centroid[0] + position[0], region.centroid[1] + position[1]];
+                region.touches_border |= x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+
+                for &(dx, dy) in connectivity.offsets() {
+                    let (nx, ny) = (x as isize + dx, y as isize + dy);
+                    if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                        continue;
+                    }
+                    let neighbour = grid.index(nx as usize, ny as usize);
+                    if labels[neighbour] == usize::MAX && grid.is_solid(nx as usize, ny as usize) == solid {
+                        labels[neighbour] = id;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+            let count = region.vertex_count as f32;
+            region.centroid = [region.centroid[0] / count, region.centroid[1] / count];
+            regions.push(region);
+        }
+        Self { width, labels, regions }
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    pub fn region_at(&self, x: usize, y: usize) -> usize {
+        self.labels[y * self.width + x]
+    }
+
+    /// Vertex labels row by row
+    pub fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    /// Solid regions
+    pub fn landmasses(&self) -> impl Iterator<Item = (usize, &Region)> + '_ {
+        self.regions.iter().enumerate().filter(|(_, it)| it.solid)
+    }
+
+    /// Empty regions enclosed by solid ones
+    pub fn lakes(&self) -> impl Iterator<Item = (usize, &Region)> + '_ {
+        self.regions.iter().enumerate().filter(|(_, it)| !it.solid && !it.touches_border)
+    }
+
+    /// Finds the regions on both sides of a contour traced from `grid` before any smoothing.
+    /// Loops winding around solid are outer boundaries, ones winding the other way are holes
+    pub fn associate(&self, grid: &WeightGrid, contour: &Contour) -> Option<LoopRegion> {
+        let (a, b) = contour
+            .points
+            .windows(2)
+            .map(|it| (it[0], it[1]))
+            .find(|(a, b)| a != b)?;
+        let solid_region = self.region_beside(grid, a, b, true)?;
+        let empty_region = self.region_beside(grid, a, b, false)?;
+        let kind = if !contour.closed {
+            LoopKind::Open
+        } else if signed_area(&contour.points) > 0.0 {
+            LoopKind::Outer
+        } else {
+            LoopKind::Hole
+        };
+        Some(LoopRegion { kind, solid_region, empty_region })
+    }
+
+    /// Region of the closest corner of the cell under segment `a`-`b` which lies on the solid (left)
+    /// or the empty (right) side of it and has a matching state
+    fn region_beside(&self, grid: &WeightGrid, a: [f32; 2], b: [f32; 2], solid: bool) -> Option<usize> {
+        let cells = grid.cell_rect();
+        let dir = [b[0] - a[0], b[1] - a[1]];
+        let side = if solid { 1.0 } else { -1.0 };
+        // nudged off the segment, so a segment along a cell edge picks the cell on the requested side
+        let nudge = 0.001 * grid.cell_size() / (dir[0] * dir[0] + dir[1] * dir[1]).sqrt();
+        let middle = [
+            (a[0] + b[0]) / 2.0 - dir[1] * nudge * side,
+            (a[1] + b[1]) / 2.0 + dir[0] * nudge * side
+        ];
+        let to_cell = |coord: f32, count: usize| {
+            ((coord / grid.cell_size()).floor().max(0.0) as usize).min(count.saturating_sub(1))
+        };
+        let (cell_x, cell_y) = (to_cell(middle[0], cells.width), to_cell(middle[1], cells.height));
+
+        let mut closest = None;
+        let mut closest_distance = f32::MAX;
+        for (x, y) in [(cell_x, cell_y), (cell_x + 1, cell_y), (cell_x, cell_y + 1), (cell_x + 1, cell_y + 1)] {
+            if x >= grid.width() || y >= grid.height() || grid.is_solid(x, y) != solid {
+                continue;
+            }
+            let corner = grid.vertex_position(x, y);
+            let left = dir[0] * (corner[1] - a[1]) - dir[1] * (corner[0] - a[0]);
+            if left * side <= 0.0 {
+                continue;
+            }
+            let distance = (corner[0] - middle[0]).powi(2) + (corner[1] - middle[1]).powi(2);
+            if distance < closest_distance {
+                closest_distance = distance;
+                closest = Some(self.region_at(x, y));
+            }
+        }
+        closest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::{WeightGrid, Connectivity};
+    use crate::terrain::contour::trace_contours;
+    use crate::terrain::regions::{RegionLabels, LoopKind};
+
+    /// A ring with a lake inside, a single vertex island and a pair of diagonal vertices
+    fn map() -> WeightGrid {
+        let mut grid = WeightGrid::new(16, 12, 1.0, 0.001);
+        for y in 2..=7 {
+            for x in 2..=7 {
+                if x == 2 || x == 7 || y == 2 || y == 7 {
+                    grid.set(x, y, 0.5);
+                }
+            }
+        }
+        grid.set(11, 4, 0.5);
+        grid.set(11, 8, 0.5);
+        grid.set(12, 9, 0.5);
+        grid
+    }
+
+    #[test]
+    pub fn test_labels_and_statistics() {
+        let grid = map();
+        let labels = RegionLabels::label(&grid, Connectivity::Four);
+        assert_eq!(4, labels.landmasses().count());
+        assert_eq!(1, labels.lakes().count());
+        assert_eq!(6, labels.regions().len());
+
+        let ring = &labels.regions()[labels.region_at(2, 2)];
+        assert!(ring.solid && !ring.touches_border);
+        assert_eq!(20, ring.vertex_count);
+        assert_eq!(((2, 2), (7, 7)), (ring.min, ring.max));
+        assert_eq!([4.5, 4.5], ring.centroid);
+
+        let (_, lake) = labels.lakes().next().unwrap();
+        assert_eq!(16, lake.vertex_count);
+        assert_eq!(labels.region_at(3, 3), labels.region_at(6, 6));
+
+        let eight = RegionLabels::label(&grid, Connectivity::Eight);
+        assert_eq!(3, eight.landmasses().count());
+        assert_eq!(labels.region_at(0, 0), labels.region_at(15, 11));
+    }
+
+    #[test]
+    pub fn test_loop_association() {
+        let grid = map();
+        let labels = RegionLabels::label(&grid, Connectivity::Four);
+        let contours = trace_contours(&grid, grid.cell_rect());
+        assert_eq!(5, contours.len());
+
+        let associations = contours
+            .iter()
+            .map(|it| labels.associate(&grid, it).unwrap())
+            .collect::<Vec<_>>();
+        let ring = labels.region_at(2, 2);
+        let lake = labels.region_at(4, 4);
+        let outside = labels.region_at(0, 0);
+        assert_eq!(4, associations.iter().filter(|it| it.kind == LoopKind::Outer).count());
+        let hole = associations.iter().find(|it| it.kind == LoopKind::Hole).unwrap();
+        assert_eq!((ring, lake), (hole.solid_region, hole.empty_region));
+        let outer = associations.iter().find(|it| it.solid_region == ring && it.kind == LoopKind::Outer).unwrap();
+        assert_eq!(outside, outer.empty_region);
+
+        let diagonal = [labels.region_at(11, 8), labels.region_at(12, 9)];
+        for region in diagonal.iter() {
+            assert_eq!(1, associations.iter().filter(|it| it.solid_region == *region).count());
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use crate::terrain::WeightGrid;
+use crate::terrain::shape::{Shape, solid_weight};
+
+/// Signed distance expression, negative inside
+#[derive(Clone, Debug)]
+pub enum Sdf {
+    /// Primitive measured by `Shape::signed_distance`
+    Shape(Shape),
+    Union(Box<Sdf>, Box<Sdf>),
+    Intersection(Box<Sdf>, Box<Sdf>),
+    /// The first shape with the second one cut out
+    Subtraction(Box<Sdf>, Box<Sdf>),
+    /// Union blending the shapes over a distance of `k`
+    SmoothUnion { a: Box<Sdf>, b: Box<Sdf>, k: f32 },
+    /// Subtraction rounding the cut edges over a distance of `k`
+    SmoothSubtraction { a: Box<Sdf>, b: Box<Sdf>, k: f32 }
+}
+
+impl Sdf {
+    pub fn circle(center: [f32; 2], radius: f32) -> Self {
+        Sdf::Shape(Shape::Circle { center, radius })
+    }
+
+    /// Axis-aligned rectangle
+    pub fn rect(center: [f32; 2], half_size: [f32; 2]) -> Self {
+        let min = [center[0] - half_size[0], center[1] - half_size[1]];
+        let max = [center[0] + half_size[0], center[1] + half_size[1]];
+        Sdf::Shape(Shape::ConvexPolygon { points: vec![min, [max[0], min[1]], max, [min[0], max[1]]] })
+    }
+
+    pub fn union(self, other: Sdf) -> Self {
+        Sdf::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn intersection(self, other: Sdf) -> Self {
+        Sdf::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn subtract(self, other: Sdf) -> Self {
+        Sdf::Subtraction(Box::new(self), Box::new(other))
+    }
+
+    pub fn smooth_union(self, other: Sdf, k: f32) -> Self {
+        Sdf::SmoothUnion { a: Box::new(self), b: Box::new(other), k }
+    }
+
+    pub fn smooth_subtract(self, other: Sdf, k: f32) -> Self {
+        Sdf::SmoothSubtraction { a: Box::new(self), b: Box::new(other), k }
+    }
+
+    /// Distance to the outline at `point`. Exact for the primitives, a bound after the boolean operations
+    pub fn distance(&self, point: [f32; 2]) -> f32 {
+        match self {
+            Sdf::Shape(shape) => shape.signed_distance(point),
+            Sdf::Union(a, b) => a.distance(point).min(b.distance(point)),
+            Sdf::Intersection(a, b) => a.distance(point).max(b.distance(point)),
+            Sdf::Subtraction(a, b) => a.distance(point).max(-b.distance(point)),
+            Sdf::SmoothUnion { a, b, k } => smooth_min(a.distance(point), b.distance(point), *k),
+            Sdf::SmoothSubtraction { a, b, k } => -smooth_min(-a.distance(point), b.distance(point), *k)
+        }
+    }
+
+    /// Samples the expression onto the vertices of `grid` with the `solid_weight` ramp `fill` uses,
+    /// so the traced iso-line follows the zero distance
+    pub fn sample_into(&self, grid: &mut WeightGrid) {
+        let cell_size = grid.cell_size();
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                let distance = self.distance(grid.vertex_position(x, y));
+                grid.set(x, y, solid_weight(distance, cell_size));
+            }
+        }
+    }
+
+    /// Grid of `width` x `height` vertices holding the sampled expression
+    pub fn sample(&self, width: usize, height: usize, cell_size: f32, iso_level: f32) -> WeightGrid {
+        let mut grid = WeightGrid::new(width, height, cell_size, iso_level);
+        self.sample_into(&mut grid);
+        grid
+    }
+}
+
+/// Polynomial smooth minimum, equal to `min` where the values differ by more than `k`
+fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b + (a - b) * h - k * h * (1.0 - h)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::contour::trace_contours;
+    use crate::terrain::measure::{measure_loop, signed_area};
+    use crate::terrain::sdf::Sdf;
+    use crate::terrain::shape::Shape;
+    use crate::terrain::WeightGrid;
+
+    #[test]
+    pub fn test_circle_gives_circular_loop() {
+        let radius = 10.0;
+        let grid = Sdf::circle([16.0, 16.0], radius).sample(33, 33, 1.0, 0.001);
+        let contours = trace_contours(&grid, grid.cell_rect());
+        assert_eq!(1, contours.len());
+        assert!(contours[0].closed);
+
+        let points = &contours[0].points;
+        for point in points.iter() {
+            let distance = ((point[0] - 16.0).powi(2) + (point[1] - 16.0).powi(2)).sqrt();
+            // crossings interpolate along the cell edges, which are oblique to the circle in most places
+            assert!((distance - radius).abs() < 0.3, "{:?} is {} away", point, distance);
+        }
+        let measure = measure_loop(points).unwrap();
+        let area = std::f32::consts::PI * radius * radius;
+        assert!((measure.signed_area - area).abs() / area < 0.03);
+        assert!((measure.perimeter - 2.0 * std::f32::consts::PI * radius).abs() < 0.5);
+        assert!((measure.centroid[0] - 16.0).abs() < 0.01 && (measure.centroid[1] - 16.0).abs() < 0.01);
+
+        // a coarser grid still lands on the circle within a fraction of a cell
+        let coarse = Sdf::circle([16.0, 16.0], radius).sample(9, 9, 4.0, 0.001);
+        let contours = trace_contours(&coarse, coarse.cell_rect());
+        assert_eq!(1, contours.len());
+        assert!(contours[0].points.iter().all(|it| {
+            (((it[0] - 16.0).powi(2) + (it[1] - 16.0).powi(2)).sqrt() - radius).abs() < 0.3 * 4.0
+        }));
+    }
+
+    #[test]
+    pub fn test_expression_tree() {
+        let rect = Sdf::rect([10.0, 10.0], [6.0, 4.0]);
+        assert_eq!(-4.0, rect.distance([10.0, 10.0]));
+        assert_eq!(5.0, rect.distance([10.0, 19.0]));
+        assert_eq!(5.0, rect.distance([19.0, 18.0]));
+
+        let left = Sdf::circle([0.0, 0.0], 2.0);
+        let right = Sdf::circle([5.0, 0.0], 2.0);
+        let union = left.clone().union(right.clone());
+        let smooth = left.clone().smooth_union(right.clone(), 3.0);
+        // the blend fills in the gap between the circles and leaves far points alone
+        assert_eq!(0.5, union.distance([2.5, 0.0]));
+        assert!(smooth.distance([2.5, 0.0]) < 0.0);
+        assert_eq!(union.distance([-5.0, 0.0]), smooth.distance([-5.0, 0.0]));
+
+        let cut = rect.clone().subtract(Sdf::circle([10.0, 10.0], 2.0));
+        assert_eq!(2.0, cut.distance([10.0, 10.0]));
+        assert_eq!(-1.0, cut.distance([15.0, 10.0]));
+        let smooth_cut = rect.clone().smooth_subtract(Sdf::circle([10.0, 10.0], 2.0), 1.0);
+        assert!(smooth_cut.distance([10.0, 10.0]) > 0.0 && smooth_cut.distance([15.0, 10.0]) < 0.0);
+
+        let grid = cut.sample(21, 21, 1.0, 0.001);
+        let contours = trace_contours(&grid, grid.cell_rect());
+        assert_eq!(2, contours.len());
+        let outer = contours.iter().map(|it| signed_area(&it.points)).fold(f32::MIN, f32::max);
+        let hole = contours.iter().map(|it| signed_area(&it.points)).fold(f32::MAX, f32::min);
+        // each sharp corner loses half a cell to the chamfer across its cell
+        assert!((outer - (12.0 * 8.0 - 4.0 * 0.5)).abs() < 0.01);
+        assert!((hole + std::f32::consts::PI * 4.0).abs() < 0.5);
+        assert!(rect.intersection(Sdf::circle([0.0, 0.0], 1.0)).distance([0.0, 0.0]) > 0.0);
+    }
+
+    #[test]
+    pub fn test_sampled_shape_matches_fill() {
+        let capsule = Shape::Capsule { a: [4.0, 5.0], b: [12.0, 9.0], radius: 2.5 };
+        let sampled = Sdf::Shape(capsule.clone()).sample(17, 17, 1.0, 0.001);
+        let mut filled = WeightGrid::new(17, 17, 1.0, 0.001);
+        filled.fill(&capsule);
+        assert_eq!(filled.weights(), sampled.weights());
+    }
+}
@@ -0,0 +1,175 @@
+use std::ops::Range;
+use crate::terrain::{WeightGrid, CellRect};
+use crate::terrain::collision::closest_point_on_segment;
+
+/// Shape for carving and filling the terrain at runtime
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Circle { center: [f32; 2], radius: f32 },
+    /// Segment from `a` to `b` inflated by `radius`
+    Capsule { a: [f32; 2], b: [f32; 2], radius: f32 },
+    /// Convex polygon in any winding order
+    ConvexPolygon { points: Vec<[f32; 2]> }
+}
+
+/// Weight of a vertex `signed_distance` away from the outline of a solid, ramping up over the cell inside it
+pub fn solid_weight(signed_distance: f32, cell_size: f32) -> f32 {
+    (-signed_distance / cell_size).clamp(0.0, 1.0)
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]) * (a[0] - b[0]) + (a[1] - b[1]) * (a[1] - b[1])).sqrt()
+}
+
+impl Shape {
+    /// Euclidean distance to the outline, negative inside
+    pub fn signed_distance(&self, point: [f32; 2]) -> f32 {
+        match self {
+            Shape::Circle { center, radius } => distance(point, *center) - radius,
+            Shape::Capsule { a, b, radius } => distance(point, closest_point_on_segment(point, *a, *b)) - radius,
+            Shape::ConvexPolygon { points } => {
+                if points.is_empty() {
+                    return f32::MAX;
+                }
+                let mut min_distance = f32::MAX;
+                let (mut left_of_all, mut right_of_all) = (true, true);
+                for (id, a) in points.iter().enumerate() {
+                    let b = points[(id + 1) % points.len()];
+                    min_distance = min_distance.min(distance(point, closest_point_on_segment(point, *a, b)));
+                    let side = (b[0] - a[0]) * (point[1] - a[1]) - (b[1] - a[1]) * (point[0] - a[0]);
+                    left_of_all &= side >= 0.0;
+                    right_of_all &= side <= 0.0;
+                }
+                if points.len() >= 3 && (left_of_all || right_of_all) {
+                    -min_distance
+                } else {
+                    min_distance
+                }
+            }
+        }
+    }
+
+    /// Axis-aligned bounds as the min and max corners
+    pub fn bounds(&self) -> ([f32; 2], [f32; 2]) {
+        match self {
+            Shape::Circle { center, radius } => (
+                [center[0] - radius, center[1] - radius],
+                [center[0] + radius, center[1] + radius]
+            ),
+            Shape::Capsule { a, b, radius } => (
+                [a[0].min(b[0]) - radius, a[1].min(b[1]) - radius],
+                [a[0].max(b[0]) + radius, a[1].max(b[1]) + radius]
+            ),
+            Shape::ConvexPolygon { points } => points.iter().fold(
+                ([f32::MAX, f32::MAX], [f32::MIN, f32::MIN]),
+                |(min, max), it| (
+                    [min[0].min(it[0]), min[1].min(it[1])],
+                    [max[0].max(it[0]), max[1].max(it[1])]
+                )
+            )
+        }
+    }
+}
+
+impl WeightGrid {
+    /// Cells having a corner among the given vertices
+    pub fn cells_around_vertices(&self, xs: Range<usize>, ys: Range<usize>) -> CellRect {
+        if xs.is_empty() || ys.is_empty() {
+            return CellRect::new(0, 0, 0, 0);
+        }
+        let first_x = xs.start.saturating_sub(1);
+        let first_y = ys.start.saturating_sub(1);
+        CellRect::new(first_x, first_y, xs.end - first_x, ys.end - first_y)
+            .intersection(&self.cell_rect())
+    }
+
+    /// Removes the shape from the solid, returns the cells whose contours changed
+    pub fn carve(&mut self, shape: &Shape) -> CellRect {
+        let cell_size = self.cell_size;
+        // the solid left over is the outside of the shape, whose distances are negated
+        self.combine(shape, |weight, signed_distance| weight.min(solid_weight(-signed_distance, cell_size)))
+    }
+
+    /// Adds the shape to the solid, returns the cells whose contours changed
+    pub fn fill(&mut self, shape: &Shape) -> CellRect {
+        let cell_size = self.cell_size;
+        self.combine(shape, |weight, signed_distance| weight.max(solid_weight(signed_distance, cell_size)))
+    }
+
+    /// Writes `combine(weight, signed distance)` into the vertices near the shape
+    fn combine(&mut self, shape: &Shape, combine: impl Fn(f32, f32) -> f32) -> CellRect {
+        let (min, max) = shape.bounds();
+        // weights stop changing a cell away from the outline
+        let margin = self.cell_size;
+        let (xs, ys) = self
+            .cells_overlapping([min[0] - margin, min[1] - margin], [max[0] + margin, max[1] + margin])
+            .vertex_ranges();
+
+        let mut changed: Option<(Range<usize>, Range<usize>)> = None;
+        for y in ys {
+            for x in xs.clone() {
+                let weight = self.get(x, y);
+                let new_weight = combine(weight, shape.signed_distance(self.vertex_position(x, y)));
+                if new_weight.to_bits() == weight.to_bits() {
+                    continue;
+                }
+                self.set(x, y, new_weight);
+                changed = Some(match changed {
+                    None => (x..x + 1, y..y + 1),
+                    Some((cx, cy)) => (cx.start.min(x)..cx.end.max(x + 1), cy.start.min(y)..cy.end.max(y + 1))
+                });
+            }
+        }
+        match changed {
+            None => CellRect::new(0, 0, 0, 0),
+            Some((xs, ys)) => self.cells_around_vertices(xs, ys)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::{WeightGrid, CellRect};
+    use crate::terrain::shape::Shape;
+
+    #[test]
+    pub fn test_shape_distances() {
+        let circle = Shape::Circle { center: [0.0, 0.0], radius: 2.0 };
+        assert_eq!(-2.0, circle.signed_distance([0.0, 0.0]));
+        assert_eq!(1.0, circle.signed_distance([3.0, 0.0]));
+
+        let capsule = Shape::Capsule { a: [0.0, 0.0], b: [10.0, 0.0], radius: 1.0 };
+        assert_eq!(-1.0, capsule.signed_distance([5.0, 0.0]));
+        assert_eq!(2.0, capsule.signed_distance([5.0, 3.0]));
+        assert_eq!(1.0, capsule.signed_distance([12.0, 0.0]));
+
+        let square = Shape::ConvexPolygon { points: vec![[0.0, 0.0], [0.0, 4.0], [4.0, 4.0], [4.0, 0.0]] };
+        assert_eq!(-1.0, square.signed_distance([1.0, 2.0]));
+        assert_eq!(3.0, square.signed_distance([7.0, 2.0]));
+        assert_eq!(5.0, square.signed_distance([7.0, 8.0]));
+        assert_eq!(([0.0, 0.0], [4.0, 4.0]), square.bounds());
+    }
+
+    #[test]
+    pub fn test_carve_and_fill() {
+        let mut grid = WeightGrid::new(33, 33, 1.0, 0.001);
+        grid.weights_mut().iter_mut().for_each(|it| *it = 1.0);
+
+        let hole = Shape::Circle { center: [10.0, 10.0], radius: 3.5 };
+        let dirty = grid.carve(&hole);
+        assert_eq!(CellRect::new(5, 5, 10, 10), dirty);
+        assert!(!grid.is_solid(10, 10) && !grid.is_solid(13, 10));
+        assert!(grid.is_solid(14, 10));
+        assert!((grid.get(14, 10) - 0.5).abs() < 0.0001);
+
+        let hit = grid.raycast([10.0, 10.0], [1.0, 0.0], 100.0).unwrap();
+        assert!((hit.point[0] - 13.5).abs() < 0.0001);
+
+        // carving the same hole again changes nothing
+        assert!(grid.carve(&hole).is_empty());
+
+        let dirty = grid.fill(&Shape::ConvexPolygon { points: vec![[8.0, 8.0], [12.0, 8.0], [12.0, 12.0], [8.0, 12.0]] });
+        assert_eq!(CellRect::new(8, 8, 4, 4), dirty);
+        assert!(grid.is_solid(10, 10) && !grid.is_solid(13, 10));
+    }
+}
@@ -0,0 +1,269 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use crate::terrain::contour::Contour;
+
+/// Vertex reduction applied to traced contours
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Simplification {
+    None,
+    /// Ramer-Douglas-Peucker: removes points closer than `tolerance` to the simplified polyline
+    DouglasPeucker { tolerance: f32 },
+    /// Visvalingam-Whyatt: removes points whose triangle with the neighbours is smaller than `tolerance²`
+    Visvalingam { tolerance: f32 }
+}
+
+impl Simplification {
+    /// Same simplification method with another tolerance
+    pub fn with_tolerance(self, tolerance: f32) -> Self {
+        match self {
+            Simplification::None => Simplification::None,
+            Simplification::DouglasPeucker { .. } => Simplification::DouglasPeucker { tolerance },
+            Simplification::Visvalingam { .. } => Simplification::Visvalingam { tolerance }
+        }
+    }
+}
+
+/// Simplifies a contour. Endpoints of open contours are kept since they lie on the border
+/// of the traced area; closed contours keep at least three points
+pub fn simplify_contour(contour: &Contour, simplification: Simplification) -> Contour {
+    let min_points = if contour.closed { 4 } else { 3 };
+    if contour.points.len() < min_points {
+        return contour.clone();
+    }
+    let keep = match simplification {
+        Simplification::None => return contour.clone(),
+        Simplification::DouglasPeucker { tolerance } => douglas_peucker(contour, tolerance),
+        Simplification::Visvalingam { tolerance } => visvalingam(contour, tolerance * tolerance)
+    };
+    Contour {
+        points: contour.points
+            .iter()
+            .zip(keep.iter())
+            .filter(|(_, keep)| **keep)
+            .map(|(point, _)| *point)
+            .collect(),
+        closed: contour.closed
+    }
+}
+
+fn distance_squared(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - b[0]) * (a[0] - b[0]) + (a[1] - b[1]) * (a[1] - b[1])
+}
+
+fn segment_distance_squared(point: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = [b[0] - a[0], b[1] - a[1]];
+    let length_squared = ab[0] * ab[0] + ab[1] * ab[1];
+    if length_squared < f32::EPSILON {
+        return distance_squared(point, a);
+    }
+    let t = (((point[0] - a[0]) * ab[0] + (point[1] - a[1]) * ab[1]) / length_squared).clamp(0.0, 1.0);
+    distance_squared(point, [a[0] + ab[0] * t, a[1] + ab[1] * t])
+}
+
+fn triangle_area(a: [f32; 2], b: [f32; 2], c: [f32; 2]) -> f32 {
+    ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])).abs() / 2.0
+}
+
+fn douglas_peucker(contour: &Contour, tolerance: f32) -> Vec<bool> {
+    let points = &contour.points;
+    let mut keep = vec![false; points.len()];
+    let tolerance_squared = tolerance * tolerance;
+
+    let mut ranges = Vec::new();
+    if contour.closed {
+        // split the loop at the first point and the point farthest from it
+        let farthest = (1..points.len())
+            .max_by(|&a, &b| {
+                distance_squared(points[0], points[a])
+                    .partial_cmp(&distance_squared(points[0], points[b]))
+                    .unwrap()
+            })
+            .unwrap();
+        keep[0] = true;
+        keep[farthest] = true;
+        ranges.push((0, farthest));
+        ranges.push((farthest, points.len()));
+    } else {
+        keep[0] = true;
+        keep[points.len() - 1] = true;
+        ranges.push((0, points.len() - 1));
+    }
+
+    while let Some((first, last)) = ranges.pop() {
+        let (a, b) = (points[first], points[last % points.len()]);
+        let mut max_distance = 0.0;
+        let mut max_id = None;
+        for (id, point) in points.iter().enumerate().take(last).skip(first + 1) {
+            let distance = segment_distance_squared(*point, a, b);
+            if distance > max_distance {
+                max_distance = distance;
+                max_id = Some(id);
+            }
+        }
+        if let Some(id) = max_id {
+            if max_distance > tolerance_squared {
+                keep[id] = true;
+                ranges.push((first, id));
+                ranges.push((id, last));
+            }
+        }
+    }
+
+    if contour.closed {
+        ensure_closed_minimum(points, &mut keep);
+    }
+    keep
+}
+
+/// Point of the Visvalingam heap, stale once `version` falls behind the point's latest area
+struct Candidate {
+    area: f32,
+    id: usize,
+    version: u32
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    // reversed, so the binary heap pops the smallest area first, the lowest index among equal ones
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.area.total_cmp(&self.area).then(other.id.cmp(&self.id))
+    }
+}
+
+fn visvalingam(contour: &Contour, area_threshold: f32) -> Vec<bool> {
+    let points = &contour.points;
+    let length = points.len();
+    let mut keep = vec![true; length];
+    let mut prev = (0..length).map(|id| (id + length - 1) % length).collect::<Vec<_>>();
+    let mut next = (0..length).map(|id| (id + 1) % length).collect::<Vec<_>>();
+    let mut versions = vec![0u32; length];
+    let mut remaining = length;
+    let min_remaining = if contour.closed { 3 } else { 2 };
+
+    let removable = |id: usize| contour.closed || (id != 0 && id != length - 1);
+    let area = |id: usize, prev: &[usize], next: &[usize]| {
+        triangle_area(points[prev[id]], points[id], points[next[id]])
+    };
+
+    let mut heap = (0..length)
+        .filter(|&id| removable(id))
+        .map(|id| Candidate { area: area(id, &prev, &next), id, version: 0 })
+        .collect::<BinaryHeap<_>>();
+    while remaining > min_remaining {
+        let Some(Candidate { area: smallest_area, id, version }) = heap.pop() else {
+            break;
+        };
+        if !keep[id] || version != versions[id] {
+            continue;
+        }
+        if smallest_area >= area_threshold {
+            break;
+        }
+        keep[id] = false;
+        remaining -= 1;
+        let (p, n) = (prev[id], next[id]);
+        next[p] = n;
+        prev[n] = p;
+        // the neighbours' triangles changed, their older heap entries become stale
+        for neighbour in [p, n] {
+            if removable(neighbour) {
+                versions[neighbour] += 1;
+                heap.push(Candidate { area: area(neighbour, &prev, &next), id: neighbour, version: versions[neighbour] });
+            }
+        }
+    }
+    keep
+}
+
+/// Degenerate loops of two points collapse into a line, so keep one more point
+fn ensure_closed_minimum(points: &[[f32; 2]], keep: &mut [bool]) {
+    if keep.iter().filter(|it| **it).count() >= 3 {
+        return;
+    }
+    let kept = (0..points.len()).filter(|&id| keep[id]).collect::<Vec<_>>();
+    let farthest = (0..points.len())
+        .filter(|&id| !keep[id])
+        .max_by(|&a, &b| {
+            let distance_a = kept.iter().map(|&k| distance_squared(points[a], points[k])).fold(f32::MAX, f32::min);
+            let distance_b = kept.iter().map(|&k| distance_squared(points[b], points[k])).fold(f32::MAX, f32::min);
+            distance_a.partial_cmp(&distance_b).unwrap()
+        });
+    if let Some(id) = farthest {
+        keep[id] = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::contour::Contour;
+    use crate::terrain::simplify::{simplify_contour, Simplification};
+
+    fn methods(tolerance: f32) -> [Simplification; 2] {
+        [
+            Simplification::DouglasPeucker { tolerance },
+            Simplification::Visvalingam { tolerance }
+        ]
+    }
+
+    #[test]
+    pub fn test_straight_line_collapses_to_endpoints() {
+        let line = Contour {
+            points: (0..=10).map(|x| [x as f32 * 10.0, (x % 2) as f32 * 0.01]).collect(),
+            closed: false
+        };
+        for method in methods(2.0) {
+            let simplified = simplify_contour(&line, method);
+            assert_eq!(vec![[0.0, 0.0], [100.0, 0.0]], simplified.points);
+        }
+    }
+
+    #[test]
+    pub fn test_closed_square_keeps_corners() {
+        let mut points = Vec::new();
+        for i in 0..10 {
+            points.push([i as f32, 0.0]);
+        }
+        for i in 0..10 {
+            points.push([10.0, i as f32]);
+        }
+        for i in 0..10 {
+            points.push([10.0 - i as f32, 10.0]);
+        }
+        for i in 0..10 {
+            points.push([0.0, 10.0 - i as f32]);
+        }
+        let square = Contour { points, closed: true };
+        for method in methods(0.5) {
+            let simplified = simplify_contour(&square, method);
+            assert!(simplified.closed);
+            assert_eq!(4, simplified.points.len());
+            for corner in [[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]] {
+                assert!(simplified.points.contains(&corner));
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_tiny_loop_stays_a_polygon() {
+        let diamond = Contour {
+            points: vec![[1.0, 0.0], [2.0, 1.0], [1.0, 2.0], [0.0, 1.0]],
+            closed: true
+        };
+        for method in methods(100.0) {
+            assert_eq!(3, simplify_contour(&diamond, method).points.len());
+        }
+    }
+}
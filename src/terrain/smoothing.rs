@@ -0,0 +1,180 @@
+use crate::terrain::contour::Contour;
+
+/// Resampling applied to traced contours to hide the grid resolution
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Smoothing {
+    None,
+    /// Centripetal Catmull-Rom spline through the contour points.
+    /// Spans are subdivided until they deviate from the spline by at most `tolerance`
+    CatmullRom { tolerance: f32 },
+    /// Chaikin corner cutting, repeated until a pass moves the outline by less than `tolerance`
+    Chaikin { tolerance: f32 }
+}
+
+impl Smoothing {
+    /// Same smoothing method with another tolerance
+    pub fn with_tolerance(self, tolerance: f32) -> Self {
+        match self {
+            Smoothing::None => Smoothing::None,
+            Smoothing::CatmullRom { .. } => Smoothing::CatmullRom { tolerance },
+            Smoothing::Chaikin { .. } => Smoothing::Chaikin { tolerance }
+        }
+    }
+}
+
+const MAX_SPAN_SUBDIVS: usize = 16;
+const MAX_CHAIKIN_ITERATIONS: usize = 5;
+
+/// Smooths a contour keeping the endpoints of open contours in place and closed ones closed
+pub fn smooth_contour(contour: &Contour, smoothing: Smoothing) -> Contour {
+    if contour.points.len() < 3 {
+        return contour.clone();
+    }
+    match smoothing {
+        Smoothing::None => contour.clone(),
+        Smoothing::CatmullRom { tolerance } => catmull_rom(contour, tolerance.max(0.001)),
+        Smoothing::Chaikin { tolerance } => chaikin(contour, tolerance.max(0.001))
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]) * (a[0] - b[0]) + (a[1] - b[1]) * (a[1] - b[1])).sqrt()
+}
+
+fn lerp(a: [f32; 2], b: [f32; 2], t: f32) -> [f32; 2] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+}
+
+/// Point on the centripetal Catmull-Rom span between `p1` and `p2` (Barry-Goldman formulation)
+fn catmull_rom_point(p0: [f32; 2], p1: [f32; 2], p2: [f32; 2], p3: [f32; 2], t: f32) -> [f32; 2] {
+    let knot = |a: [f32; 2], b: [f32; 2]| distance(a, b).sqrt().max(0.0001);
+    let t0 = 0.0;
+    let t1 = t0 + knot(p0, p1);
+    let t2 = t1 + knot(p1, p2);
+    let t3 = t2 + knot(p2, p3);
+    let t = t1 + (t2 - t1) * t;
+
+    let a1 = lerp(p0, p1, (t - t0) / (t1 - t0));
+    let a2 = lerp(p1, p2, (t - t1) / (t2 - t1));
+    let a3 = lerp(p2, p3, (t - t2) / (t3 - t2));
+    let b1 = lerp(a1, a2, (t - t0) / (t2 - t0));
+    let b2 = lerp(a2, a3, (t - t1) / (t3 - t1));
+    lerp(b1, b2, (t - t1) / (t2 - t1))
+}
+
+fn catmull_rom(contour: &Contour, tolerance: f32) -> Contour {
+    let points = &contour.points;
+    let length = points.len();
+    let get = |id: isize| -> [f32; 2] {
+        if contour.closed {
+            points[id.rem_euclid(length as isize) as usize]
+        } else if id < 0 {
+            // reflected phantom point keeps the tangent at the open end
+            let (first, second) = (points[0], points[1]);
+            [2.0 * first[0] - second[0], 2.0 * first[1] - second[1]]
+        } else if id as usize >= length {
+            let (last, before_last) = (points[length - 1], points[length - 2]);
+            [2.0 * last[0] - before_last[0], 2.0 * last[1] - before_last[1]]
+        } else {
+            points[id as usize]
+        }
+    };
+
+    let num_spans = if contour.closed { length } else { length - 1 };
+    let mut result = Vec::with_capacity(num_spans * 2);
+    for span in 0..num_spans as isize {
+        let (p0, p1, p2, p3) = (get(span - 1), get(span), get(span + 1), get(span + 2));
+        let mid = catmull_rom_point(p0, p1, p2, p3, 0.5);
+        let deviation = distance(mid, lerp(p1, p2, 0.5));
+        let num_subdivs = ((deviation / tolerance).sqrt().ceil() as usize).clamp(1, MAX_SPAN_SUBDIVS);
+
+        result.push(p1);
+        for ix in 1..num_subdivs {
+            result.push(catmull_rom_point(p0, p1, p2, p3, ix as f32 / num_subdivs as f32));
+        }
+    }
+    if !contour.closed {
+        result.push(points[length - 1]);
+    }
+    Contour { points: result, closed: contour.closed }
+}
+
+fn chaikin(contour: &Contour, tolerance: f32) -> Contour {
+    let mut points = contour.points.clone();
+    for _ in 0..MAX_CHAIKIN_ITERATIONS {
+        let length = points.len();
+        let mut result = Vec::with_capacity(length * 2);
+        let mut max_shift: f32 = 0.0;
+
+        if !contour.closed {
+            result.push(points[0]);
+        }
+        let num_corners = if contour.closed { length } else { length - 2 };
+        for corner in 0..num_corners {
+            let (prev, current, next) = if contour.closed {
+                (points[(corner + length - 1) % length], points[corner], points[(corner + 1) % length])
+            } else {
+                (points[corner], points[corner + 1], points[corner + 2])
+            };
+            let cut_in = lerp(current, prev, 0.25);
+            let cut_out = lerp(current, next, 0.25);
+            max_shift = max_shift.max(distance(current, lerp(cut_in, cut_out, 0.5)));
+            result.push(cut_in);
+            result.push(cut_out);
+        }
+        if !contour.closed {
+            result.push(points[length - 1]);
+        }
+
+        points = result;
+        if max_shift < tolerance {
+            break;
+        }
+    }
+    Contour { points, closed: contour.closed }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::terrain::contour::Contour;
+    use crate::terrain::smoothing::{smooth_contour, Smoothing};
+
+    fn square() -> Contour {
+        Contour {
+            points: vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]],
+            closed: true
+        }
+    }
+
+    #[test]
+    pub fn test_catmull_rom_passes_through_points() {
+        let smoothed = smooth_contour(&square(), Smoothing::CatmullRom { tolerance: 0.05 });
+        assert!(smoothed.closed);
+        assert!(smoothed.points.len() > 4);
+        for corner in square().points {
+            assert!(smoothed.points.contains(&corner));
+        }
+    }
+
+    #[test]
+    pub fn test_open_endpoints_are_kept() {
+        let open = Contour {
+            points: vec![[0.0, 0.0], [5.0, 5.0], [10.0, 0.0], [15.0, 5.0]],
+            closed: false
+        };
+        for smoothing in [Smoothing::CatmullRom { tolerance: 0.1 }, Smoothing::Chaikin { tolerance: 0.1 }] {
+            let smoothed = smooth_contour(&open, smoothing);
+            assert!(!smoothed.closed);
+            assert_eq!([0.0, 0.0], smoothed.points[0]);
+            assert_eq!([15.0, 5.0], *smoothed.points.last().unwrap());
+        }
+    }
+
+    #[test]
+    pub fn test_chaikin_stops_at_tolerance() {
+        let coarse = smooth_contour(&square(), Smoothing::Chaikin { tolerance: 2.0 });
+        let fine = smooth_contour(&square(), Smoothing::Chaikin { tolerance: 0.01 });
+        assert!(coarse.points.len() < fine.points.len());
+        assert!(fine.closed);
+    }
+}